@@ -0,0 +1,114 @@
+//! How finding rooms and sending events to them behaves as the amount of rooms
+//! grows, e.g., `cargo bench --bench game_state`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rocket::tokio;
+use skribbl::{events, game, room, state, word_packs};
+
+const USERS_PER_ROOM: usize = 8;
+
+fn user(room_id: &str, index: usize) -> state::User {
+    state::UserBuilder::default()
+        .id(format!("{}-{}", room_id, index))
+        .display_name(format!("user {}", index))
+        .room_id(room_id.to_string())
+        .build()
+        .unwrap()
+}
+
+/// Rooms full of users, each run by its own task, along with the rooms and the
+/// resume tokens of the users in them.
+async fn rooms(
+    amount: usize,
+    game_state: &state::GameState,
+) -> (Vec<(String, room::RoomHandle)>, Vec<String>) {
+    let word_packs = word_packs::WordPacks::default();
+    let mut rooms = vec![];
+    let mut resume_tokens = vec![];
+
+    for i in 0..amount {
+        let room_id = format!("room-{}", i);
+        let host = user(&room_id, 0);
+        let room = state::RoomBuilder::default()
+            .id(room_id.clone())
+            .host_id(host.id.clone())
+            .max_users(USERS_PER_ROOM as u8)
+            .build()
+            .unwrap();
+
+        resume_tokens.push(host.resume_token.clone());
+
+        let handle =
+            room::create(game::Room::new(room, host, word_packs.clone()), game_state)
+                .await;
+
+        for j in 1..USERS_PER_ROOM {
+            let user = user(&room_id, j);
+
+            resume_tokens.push(user.resume_token.clone());
+            handle.join(user, false).await.unwrap();
+        }
+
+        rooms.push((room_id, handle));
+    }
+
+    (rooms, resume_tokens)
+}
+
+fn bench_game_state(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("game_state");
+
+    for amount in [100, 1_000, 10_000] {
+        // The task that keeps the rooms up to date runs on the runtime.
+        let game_state = runtime.block_on(async { state::GameState::default() });
+        let (rooms, resume_tokens) = runtime.block_on(rooms(amount, &game_state));
+
+        group.bench_with_input(
+            BenchmarkId::new("find_by_resume_token", amount),
+            &amount,
+            |b, _| {
+                let mut i = 0;
+
+                b.iter(|| {
+                    i += 1;
+
+                    runtime.block_on(async {
+                        game_state
+                            .rooms
+                            .lock()
+                            .await
+                            .find_by_resume_token(&resume_tokens[i % resume_tokens.len()])
+                            .is_some()
+                    })
+                });
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("send_event", amount),
+            &amount,
+            |b, _| {
+                let mut i = 0;
+
+                b.iter(|| {
+                    let (room_id, room) = &rooms[i % rooms.len()];
+                    let user_id = format!("{}-{}", room_id, USERS_PER_ROOM - 1);
+
+                    i += 1;
+
+                    runtime.block_on(room.send_event(
+                        &user_id,
+                        events::ClientToServerEvents::Message {
+                            message: "hello".to_string(),
+                        },
+                    ))
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_game_state);
+criterion_main!(benches);
@@ -3,3 +3,5 @@ export const MIN_DISPLAY_NAME_LENGTH = 3;
 export const wsProtocol = import.meta.env.DEV ? "ws" : "wss";
 export const wsHost = window.location.host;
 export const MAX_CHAT_MSG_LENGTH = 255;
+/** The version of the binary protocol this client speaks. */
+export const BINARY_PROTOCOL_VERSION = 1;
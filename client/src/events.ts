@@ -36,7 +36,7 @@ export function handleError(data: Array<number>) {
         return;
     }
 
-    toast.error(parsePartOfBinaryData(data, "string"));
+    toast.error(parseErrorMessage(data));
 }
 
 export function handleConnectError(data: Array<number>) {
@@ -44,10 +44,23 @@ export function handleConnectError(data: Array<number>) {
         return;
     }
 
-    toast.error(parsePartOfBinaryData(data, "string"));
+    toast.error(parseErrorMessage(data));
     STATE.socket.ws.close();
 }
 
+/**
+ * Errors are an error code followed by an optional message.
+ */
+function parseErrorMessage(data: Array<number>) {
+    const code = parsePartOfBinaryData(data, "uint8");
+
+    if (data.length === 0) {
+        return `Something went wrong (error ${code})`;
+    }
+
+    return parsePartOfBinaryData(data, "string");
+}
+
 export function handleUserJoined(data: Array<number>) {
     if (STATE.socket.connectionState !== "connected") {
         return;
@@ -594,3 +607,65 @@ export function handleRevealWord(data: Array<number>) {
 
     getWordToDrawEl().textContent = word;
 }
+
+export function handleHint(data: Array<number>) {
+    const word = parsePartOfBinaryData(data, "string");
+
+    getWordToDrawEl().textContent = word;
+}
+
+export function handleCloseGuess(data: Array<number>) {
+    const guess = parsePartOfBinaryData(data, "string");
+
+    const li = document.createElement("li");
+    const messageEl = document.createElement("div");
+
+    li.setAttribute("data-message", "system");
+    messageEl.textContent = `${guess} is close!`;
+
+    li.appendChild(messageEl);
+
+    getListOfChatsContainer().appendChild(li);
+
+    li.scrollIntoView({ behavior: "smooth", block: "end" });
+}
+
+export function handleCanvasSnapshot(data: Array<number>) {
+    const actions = JSON.parse(parsePartOfBinaryData(data, "string"));
+
+    if (!Array.isArray(actions)) {
+        throw new Error("Received invalid payload from server");
+    }
+
+    const canvas =
+        STATE.canvas ?? new Canvas(getDrawingCanvas().getContext("2d")!);
+
+    STATE.canvas = canvas;
+
+    for (const action of actions) {
+        switch (action.type) {
+            case "pointerDown":
+                canvas.isDrawing = true;
+                break;
+            case "pointerMove":
+                canvas.drawLine(action.x, action.y);
+                break;
+            case "pointerUp":
+                canvas.isDrawing = false;
+                canvas.resetPrevPoint();
+                break;
+            case "pointerLeave":
+                canvas.resetPrevPoint();
+                break;
+            case "strokeBatch":
+                for (const point of action.points) {
+                    canvas.drawLine(point.x, point.y);
+                }
+                break;
+            case "changeColor":
+                break;
+            default:
+                throw new Error("Received invalid canvas action from server");
+        }
+    }
+}
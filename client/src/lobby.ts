@@ -1,4 +1,4 @@
-import { wsHost, wsProtocol } from "./consts";
+import { BINARY_PROTOCOL_VERSION, wsHost, wsProtocol } from "./consts";
 import { getChatContainer, initializeWaitingRoom } from "./dom";
 import { toast } from "./lib/toast";
 import { HTMLElementListener, WebSocketListener } from "./listener";
@@ -53,14 +53,21 @@ async function connectToSocket(mode: "play" | "create") {
             "Welcome to Skribbl",
             processError,
             async () => {
-                const binaryProtocolVersion = await (
+                const { min, max } = await (
                     await fetch("/ws/binary-protocol-version")
                 ).json();
 
-                STATE.binaryProtocolVersion = binaryProtocolVersion;
+                if (
+                    BINARY_PROTOCOL_VERSION < min ||
+                    BINARY_PROTOCOL_VERSION > max
+                ) {
+                    throw "Binary protocol version mismatch";
+                }
+
+                STATE.binaryProtocolVersion = BINARY_PROTOCOL_VERSION;
 
                 const ws = await connect(
-                    `${wsProtocol}://${wsHost}/ws?displayName=${displayName}&roomId=${roomId}&mode=${mode}`,
+                    `${wsProtocol}://${wsHost}/ws?displayName=${displayName}&roomId=${roomId}&mode=${mode}&protocolVersions=${BINARY_PROTOCOL_VERSION}`,
                 );
 
                 return ws;
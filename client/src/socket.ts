@@ -20,10 +20,13 @@ import {
 } from "./dom";
 import {
     handleAddScore,
+    handleCanvasSnapshot,
+    handleCloseGuess,
     handleChangeColor,
     handleConnectError,
     handleEndGame,
     handleError,
+    handleHint,
     handleNewHost,
     handleNewRound,
     handleNewTurn,
@@ -208,6 +211,15 @@ export function wsOnMessage(e: MessageEvent) {
             case ServerToClientEvents.RevealWord:
                 handleRevealWord(data);
                 break;
+            case ServerToClientEvents.Hint:
+                handleHint(data);
+                break;
+            case ServerToClientEvents.CloseGuess:
+                handleCloseGuess(data);
+                break;
+            case ServerToClientEvents.CanvasSnapshot:
+                handleCanvasSnapshot(data);
+                break;
             default:
                 throw new Error("Received invalid event from server.");
         }
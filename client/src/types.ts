@@ -110,4 +110,7 @@ export enum ServerToClientEvents {
     UserGuessed = 21,
     SystemMessage = 22,
     RevealWord = 23,
+    Hint = 24,
+    CloseGuess = 25,
+    CanvasSnapshot = 27,
 }
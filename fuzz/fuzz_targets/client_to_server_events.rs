@@ -0,0 +1,23 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use skribbl::events::{BinaryCodec, ClientToServerEvents};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(event) = ClientToServerEvents::try_from(&data.to_vec()) else {
+        return;
+    };
+
+    // Anything that decodes must survive being sent again in the same version.
+    let version = data[0].try_into().unwrap();
+    let bytes = event.encode_with_version(version).unwrap();
+    let decoded = ClientToServerEvents::decode(&bytes).unwrap();
+
+    if let ClientToServerEvents::PointerMove { x, y } = event {
+        if x.is_nan() || y.is_nan() {
+            return;
+        }
+    }
+
+    assert_eq!(decoded, event);
+});
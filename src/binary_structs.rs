@@ -0,0 +1,610 @@
+//! Native binary layouts for the structs sent in events, used from version 3 of
+//! the binary protocol instead of embedding them as JSON.
+//!
+//! A struct is a list of tagged fields, each being `[tag][length][data]` where the
+//! tag is a single byte and the length is a LEB128 varint. Fields can come in any
+//! order, fields that are not needed are left out, and unknown tags are skipped,
+//! so new fields can be added without breaking older clients.
+//!
+//! A list is its elements one after another, each being `[length][data]`.
+//! Strings are UTF-8, integers are big endian, and booleans are a single byte
+//! that is either 0 or 1.
+//!
+//! The tags of every struct are in the `*_tags` modules below and must never be
+//! reused for a different field.
+
+use crate::{state, utils};
+
+/// Tags of `state::User`.
+pub mod user_tags {
+    /// String.
+    pub const ID: u8 = 1;
+    /// String.
+    pub const DISPLAY_NAME: u8 = 2;
+    /// Boolean.
+    pub const HAS_DRAWN: u8 = 3;
+    /// Boolean.
+    pub const HAS_GUESSED: u8 = 4;
+    /// `u16`.
+    pub const SCORE: u8 = 5;
+    /// Boolean.
+    pub const IS_SPECTATOR: u8 = 6;
+}
+
+/// Tags of `state::Room`.
+pub mod room_tags {
+    /// String.
+    pub const ID: u8 = 1;
+    /// String.
+    pub const HOST_ID: u8 = 2;
+    /// `u8`, 0 for public and 1 for private.
+    pub const VISIBILITY: u8 = 3;
+    /// `state::RoomState`.
+    pub const STATE: u8 = 4;
+    /// `u8`.
+    pub const MAX_USERS: u8 = 5;
+    /// `u8`.
+    pub const MAX_ROUNDS: u8 = 6;
+    /// `u8`.
+    pub const DRAW_TIME_LIMIT: u8 = 7;
+    /// `u8`.
+    pub const PICK_WORD_TIME_LIMIT: u8 = 8;
+    /// `u8`.
+    pub const AMOUNT_OF_WORDS_TO_PICK: u8 = 9;
+}
+
+/// Tags of `state::RoomState`.
+pub mod room_state_tags {
+    /// `u8`, 0 for waiting, 1 for playing, and 2 for finished.
+    pub const KIND: u8 = 1;
+    /// `state::PlayingState`, only when playing.
+    pub const PLAYING_STATE: u8 = 2;
+    /// String, only when playing.
+    pub const CURRENT_USER_ID: u8 = 3;
+    /// `u8`, only when playing.
+    pub const CURRENT_ROUND: u8 = 4;
+}
+
+/// Tags of `state::PlayingState`.
+pub mod playing_state_tags {
+    /// `u8`, 0 for picking a word and 1 for drawing.
+    pub const KIND: u8 = 1;
+    /// List of strings, only when picking a word.
+    pub const WORDS_TO_PICK: u8 = 2;
+    /// String, only when drawing.
+    pub const CURRENT_WORD: u8 = 3;
+}
+
+/// Tags of `state::CanvasAction`.
+pub mod canvas_action_tags {
+    /// `u8`, 0 for pointer down, 1 for pointer move, 2 for pointer up, 3 for
+    /// pointer leave, 4 for change color, and 5 for a stroke batch.
+    pub const KIND: u8 = 1;
+    /// `f64`, only when moving the pointer.
+    pub const X: u8 = 2;
+    /// `f64`, only when moving the pointer.
+    pub const Y: u8 = 3;
+    /// String, only when changing color.
+    pub const COLOR: u8 = 4;
+    /// Stroke points as encoded by `utils::encode_stroke_points`, only for a
+    /// stroke batch.
+    pub const POINTS: u8 = 5;
+}
+
+/// A value that has a native binary layout.
+pub trait BinaryValue: Sized {
+    fn to_binary(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
+    fn from_binary(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>>;
+}
+
+/// Reads the `[length][data]` that starts at `start` and returns the data along
+/// with the position right after it.
+fn read_length_prefixed(
+    data: &[u8],
+    start: usize,
+) -> Result<(&[u8], usize), Box<dyn std::error::Error>> {
+    let (length, length_end_position) = utils::read_varint(data, start)?;
+
+    if length > utils::consts::MAX_PAYLOAD_SIZE {
+        return Err("Payload is too large".into());
+    }
+
+    let data_end_position = length_end_position + length;
+    let value = data
+        .get(length_end_position..data_end_position)
+        .ok_or("Data is too short")?;
+
+    Ok((value, data_end_position))
+}
+
+fn write_length_prefixed(bytes: &mut Vec<u8>, data: &[u8]) {
+    bytes.extend(utils::turn_usize_to_varint(data.len()));
+    bytes.extend_from_slice(data);
+}
+
+/// Builds the tagged fields of a struct.
+#[derive(Default)]
+pub struct TaggedFieldsWriter {
+    bytes: Vec<u8>,
+}
+
+impl TaggedFieldsWriter {
+    pub fn bytes(&mut self, tag: u8, data: &[u8]) -> &mut Self {
+        self.bytes.push(tag);
+        write_length_prefixed(&mut self.bytes, data);
+
+        self
+    }
+
+    pub fn u8(&mut self, tag: u8, value: u8) -> &mut Self {
+        self.bytes(tag, &[value])
+    }
+
+    pub fn bool(&mut self, tag: u8, value: bool) -> &mut Self {
+        self.u8(tag, value.into())
+    }
+
+    pub fn u16(&mut self, tag: u8, value: u16) -> &mut Self {
+        self.bytes(tag, &value.to_be_bytes())
+    }
+
+    pub fn f64(&mut self, tag: u8, value: f64) -> &mut Self {
+        self.bytes(tag, &value.to_be_bytes())
+    }
+
+    pub fn value(
+        &mut self,
+        tag: u8,
+        value: &impl BinaryValue,
+    ) -> Result<&mut Self, Box<dyn std::error::Error>> {
+        Ok(self.bytes(tag, &value.to_binary()?))
+    }
+
+    pub fn finish(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.bytes)
+    }
+}
+
+/// The tagged fields of a struct that is being decoded.
+pub struct TaggedFields<'a> {
+    fields: Vec<(u8, &'a [u8])>,
+}
+
+impl<'a> TaggedFields<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut fields = vec![];
+        let mut position = 0;
+
+        while position < data.len() {
+            let tag = data[position];
+            let (value, end_position) = read_length_prefixed(data, position + 1)?;
+
+            fields.push((tag, value));
+            position = end_position;
+        }
+
+        Ok(Self { fields })
+    }
+
+    pub fn get(&self, tag: u8) -> Option<&'a [u8]> {
+        self.fields
+            .iter()
+            .find(|(field_tag, _)| *field_tag == tag)
+            .map(|(_, value)| *value)
+    }
+
+    pub fn bytes(&self, tag: u8) -> Result<&'a [u8], Box<dyn std::error::Error>> {
+        self.get(tag)
+            .ok_or_else(|| format!("Missing field with tag {}", tag).into())
+    }
+
+    pub fn u8(&self, tag: u8) -> Result<u8, Box<dyn std::error::Error>> {
+        let [value] = self.bytes(tag)? else {
+            return Err("Data is invalid".into());
+        };
+
+        Ok(*value)
+    }
+
+    pub fn bool(&self, tag: u8) -> Result<bool, Box<dyn std::error::Error>> {
+        match self.u8(tag)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err("Data is invalid".into()),
+        }
+    }
+
+    pub fn u16(&self, tag: u8) -> Result<u16, Box<dyn std::error::Error>> {
+        Ok(u16::from_be_bytes(
+            self.bytes(tag)?
+                .try_into()
+                .ok()
+                .ok_or("Data is invalid")?,
+        ))
+    }
+
+    pub fn f64(&self, tag: u8) -> Result<f64, Box<dyn std::error::Error>> {
+        Ok(f64::from_be_bytes(
+            self.bytes(tag)?
+                .try_into()
+                .ok()
+                .ok_or("Data is invalid")?,
+        ))
+    }
+
+    pub fn value<T: BinaryValue>(
+        &self,
+        tag: u8,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        T::from_binary(self.bytes(tag)?)
+    }
+}
+
+impl BinaryValue for String {
+    fn to_binary(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        Ok(self.as_bytes().to_vec())
+    }
+
+    fn from_binary(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(String::from_utf8(data.to_vec())?)
+    }
+}
+
+impl<T: BinaryValue> BinaryValue for Vec<T> {
+    fn to_binary(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut bytes = vec![];
+
+        for element in self {
+            write_length_prefixed(&mut bytes, &element.to_binary()?);
+        }
+
+        Ok(bytes)
+    }
+
+    fn from_binary(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut elements = vec![];
+        let mut position = 0;
+
+        while position < data.len() {
+            let (element, end_position) = read_length_prefixed(data, position)?;
+
+            elements.push(T::from_binary(element)?);
+            position = end_position;
+        }
+
+        Ok(elements)
+    }
+}
+
+impl<T: BinaryValue> BinaryValue for Box<T> {
+    fn to_binary(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        (**self).to_binary()
+    }
+
+    fn from_binary(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Box::new(T::from_binary(data)?))
+    }
+}
+
+impl BinaryValue for state::User {
+    fn to_binary(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        Ok(TaggedFieldsWriter::default()
+            .value(user_tags::ID, &self.id)?
+            .value(user_tags::DISPLAY_NAME, &self.display_name)?
+            .bool(user_tags::HAS_DRAWN, self.has_drawn)
+            .bool(user_tags::HAS_GUESSED, self.has_guessed)
+            .u16(user_tags::SCORE, self.score)
+            .bool(user_tags::IS_SPECTATOR, self.is_spectator)
+            .finish())
+    }
+
+    fn from_binary(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let fields = TaggedFields::parse(data)?;
+
+        Ok(state::User {
+            id: fields.value(user_tags::ID)?,
+            display_name: fields.value(user_tags::DISPLAY_NAME)?,
+            room_id: String::new(),
+            has_drawn: fields.bool(user_tags::HAS_DRAWN)?,
+            has_guessed: fields.bool(user_tags::HAS_GUESSED)?,
+            score: fields.u16(user_tags::SCORE)?,
+            resume_token: String::new(),
+            connection_id: String::new(),
+            is_connected: false,
+            is_spectator: fields.bool(user_tags::IS_SPECTATOR)?,
+        })
+    }
+}
+
+impl BinaryValue for state::PlayingState {
+    fn to_binary(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut writer = TaggedFieldsWriter::default();
+
+        match self {
+            state::PlayingState::PickingAWord { words_to_pick, .. } => writer
+                .u8(playing_state_tags::KIND, 0)
+                .value(playing_state_tags::WORDS_TO_PICK, words_to_pick)?,
+            state::PlayingState::Drawing { current_word, .. } => writer
+                .u8(playing_state_tags::KIND, 1)
+                .value(playing_state_tags::CURRENT_WORD, current_word)?,
+        };
+
+        Ok(writer.finish())
+    }
+
+    fn from_binary(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let fields = TaggedFields::parse(data)?;
+
+        match fields.u8(playing_state_tags::KIND)? {
+            0 => Ok(state::PlayingState::PickingAWord {
+                words_to_pick: fields.value(playing_state_tags::WORDS_TO_PICK)?,
+                time_left: 0,
+            }),
+            1 => Ok(state::PlayingState::Drawing {
+                current_word: fields.value(playing_state_tags::CURRENT_WORD)?,
+                time_left: 0,
+                revealed_indices: vec![],
+            }),
+            _ => Err("Invalid playing state".into()),
+        }
+    }
+}
+
+impl BinaryValue for state::RoomState {
+    fn to_binary(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut writer = TaggedFieldsWriter::default();
+
+        match self {
+            state::RoomState::Waiting => writer.u8(room_state_tags::KIND, 0),
+            state::RoomState::Playing {
+                playing_state,
+                current_user_id,
+                current_round,
+            } => writer
+                .u8(room_state_tags::KIND, 1)
+                .value(room_state_tags::PLAYING_STATE, playing_state)?
+                .value(room_state_tags::CURRENT_USER_ID, current_user_id)?
+                .u8(room_state_tags::CURRENT_ROUND, *current_round),
+            state::RoomState::Finished => writer.u8(room_state_tags::KIND, 2),
+        };
+
+        Ok(writer.finish())
+    }
+
+    fn from_binary(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let fields = TaggedFields::parse(data)?;
+
+        match fields.u8(room_state_tags::KIND)? {
+            0 => Ok(state::RoomState::Waiting),
+            1 => Ok(state::RoomState::Playing {
+                playing_state: fields.value(room_state_tags::PLAYING_STATE)?,
+                current_user_id: fields.value(room_state_tags::CURRENT_USER_ID)?,
+                current_round: fields.u8(room_state_tags::CURRENT_ROUND)?,
+            }),
+            2 => Ok(state::RoomState::Finished),
+            _ => Err("Invalid room state".into()),
+        }
+    }
+}
+
+impl BinaryValue for state::Room {
+    fn to_binary(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let visibility = match self.visibility {
+            state::Visibility::Public => 0,
+            state::Visibility::Private => 1,
+        };
+
+        Ok(TaggedFieldsWriter::default()
+            .value(room_tags::ID, &self.id)?
+            .value(room_tags::HOST_ID, &self.host_id)?
+            .u8(room_tags::VISIBILITY, visibility)
+            .value(room_tags::STATE, &self.state)?
+            .u8(room_tags::MAX_USERS, self.max_users)
+            .u8(room_tags::MAX_ROUNDS, self.max_rounds)
+            .u8(room_tags::DRAW_TIME_LIMIT, self.draw_time_limit)
+            .u8(room_tags::PICK_WORD_TIME_LIMIT, self.pick_word_time_limit)
+            .u8(
+                room_tags::AMOUNT_OF_WORDS_TO_PICK,
+                self.amount_of_words_to_pick,
+            )
+            .finish())
+    }
+
+    fn from_binary(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let fields = TaggedFields::parse(data)?;
+        let visibility = match fields.u8(room_tags::VISIBILITY)? {
+            0 => state::Visibility::Public,
+            1 => state::Visibility::Private,
+            _ => return Err("Invalid visibility".into()),
+        };
+
+        Ok(state::Room {
+            id: fields.value(room_tags::ID)?,
+            host_id: fields.value(room_tags::HOST_ID)?,
+            visibility,
+            state: fields.value(room_tags::STATE)?,
+            max_users: fields.u8(room_tags::MAX_USERS)?,
+            max_rounds: fields.u8(room_tags::MAX_ROUNDS)?,
+            draw_time_limit: fields.u8(room_tags::DRAW_TIME_LIMIT)?,
+            pick_word_time_limit: fields.u8(room_tags::PICK_WORD_TIME_LIMIT)?,
+            amount_of_words_to_pick: fields.u8(room_tags::AMOUNT_OF_WORDS_TO_PICK)?,
+            amount_of_users: 0,
+            word_source: state::WordSource::default(),
+            canvas: vec![],
+            canvas_points: 0,
+        })
+    }
+}
+
+impl BinaryValue for state::CanvasAction {
+    fn to_binary(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut writer = TaggedFieldsWriter::default();
+
+        match self {
+            state::CanvasAction::PointerDown => writer.u8(canvas_action_tags::KIND, 0),
+            state::CanvasAction::PointerMove { x, y } => writer
+                .u8(canvas_action_tags::KIND, 1)
+                .f64(canvas_action_tags::X, *x)
+                .f64(canvas_action_tags::Y, *y),
+            state::CanvasAction::PointerUp => writer.u8(canvas_action_tags::KIND, 2),
+            state::CanvasAction::PointerLeave => writer.u8(canvas_action_tags::KIND, 3),
+            state::CanvasAction::ChangeColor { color } => writer
+                .u8(canvas_action_tags::KIND, 4)
+                .value(canvas_action_tags::COLOR, color)?,
+            state::CanvasAction::StrokeBatch { points } => {
+                writer.u8(canvas_action_tags::KIND, 5).bytes(
+                    canvas_action_tags::POINTS,
+                    &utils::encode_stroke_points(points),
+                )
+            }
+        };
+
+        Ok(writer.finish())
+    }
+
+    fn from_binary(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let fields = TaggedFields::parse(data)?;
+
+        match fields.u8(canvas_action_tags::KIND)? {
+            0 => Ok(state::CanvasAction::PointerDown),
+            1 => Ok(state::CanvasAction::PointerMove {
+                x: fields.f64(canvas_action_tags::X)?,
+                y: fields.f64(canvas_action_tags::Y)?,
+            }),
+            2 => Ok(state::CanvasAction::PointerUp),
+            3 => Ok(state::CanvasAction::PointerLeave),
+            4 => Ok(state::CanvasAction::ChangeColor {
+                color: fields.value(canvas_action_tags::COLOR)?,
+            }),
+            5 => Ok(state::CanvasAction::StrokeBatch {
+                points: utils::decode_stroke_points(
+                    fields.bytes(canvas_action_tags::POINTS)?,
+                )?,
+            }),
+            _ => Err("Invalid canvas action".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_layout() {
+        let user = state::UserBuilder::default()
+            .id("ab".to_string())
+            .display_name("cd".to_string())
+            .room_id("room".to_string())
+            .score(300)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            user.to_binary().unwrap(),
+            vec![
+                user_tags::ID,
+                2,
+                b'a',
+                b'b',
+                user_tags::DISPLAY_NAME,
+                2,
+                b'c',
+                b'd',
+                user_tags::HAS_DRAWN,
+                1,
+                0,
+                user_tags::HAS_GUESSED,
+                1,
+                0,
+                user_tags::SCORE,
+                2,
+                1,
+                44,
+                user_tags::IS_SPECTATOR,
+                1,
+                0,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_room_round_trip() {
+        let room = state::RoomBuilder::default()
+            .id("room".to_string())
+            .host_id("host".to_string())
+            .visibility(state::Visibility::Private)
+            .amount_of_users(0)
+            .state(state::RoomState::Playing {
+                playing_state: state::PlayingState::PickingAWord {
+                    words_to_pick: vec!["apple".to_string(), "pear".to_string()],
+                    time_left: 0,
+                },
+                current_user_id: "host".to_string(),
+                current_round: 2,
+            })
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            state::Room::from_binary(&room.to_binary().unwrap()).unwrap(),
+            room
+        );
+    }
+
+    #[test]
+    fn test_fields_can_come_in_any_order_and_unknown_tags_are_skipped() {
+        let data = TaggedFieldsWriter::default()
+            .u8(200, 1)
+            .value(playing_state_tags::CURRENT_WORD, &"apple".to_string())
+            .unwrap()
+            .u8(playing_state_tags::KIND, 1)
+            .finish();
+
+        assert_eq!(
+            state::PlayingState::from_binary(&data).unwrap(),
+            state::PlayingState::Drawing {
+                current_word: "apple".to_string(),
+                time_left: 0,
+                revealed_indices: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_missing_fields_fail() {
+        let data = TaggedFieldsWriter::default()
+            .u8(playing_state_tags::KIND, 1)
+            .finish();
+
+        assert!(state::PlayingState::from_binary(&data).is_err());
+        assert!(
+            state::PlayingState::from_binary(&[playing_state_tags::KIND, 5]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_canvas_actions_round_trip() {
+        let actions = vec![
+            state::CanvasAction::ChangeColor {
+                color: "#ff0000".to_string(),
+            },
+            state::CanvasAction::PointerDown,
+            state::CanvasAction::PointerMove { x: 0.5, y: -1.25 },
+            state::CanvasAction::PointerUp,
+            state::CanvasAction::PointerLeave,
+            state::CanvasAction::StrokeBatch {
+                points: vec![
+                    state::StrokePoint { x: 10, y: 20 },
+                    state::StrokePoint { x: 12, y: 18 },
+                ],
+            },
+        ];
+
+        assert_eq!(
+            Vec::<state::CanvasAction>::from_binary(&actions.to_binary().unwrap())
+                .unwrap(),
+            actions
+        );
+    }
+}
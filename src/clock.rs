@@ -0,0 +1,94 @@
+//! What the timers of the rooms run on, so tests can move time forward instead
+//! of waiting for it.
+
+use rocket::futures::future::BoxFuture;
+use rocket::tokio;
+
+pub trait Clock: Send + Sync {
+    /// The time since the clock started.
+    fn now(&self) -> tokio::time::Duration;
+
+    /// Waits until `now` is at least `deadline`.
+    fn sleep_until(&self, deadline: tokio::time::Duration) -> BoxFuture<'static, ()>;
+}
+
+/// The time kept by tokio, which also follows `tokio::time::pause`.
+pub struct TokioClock {
+    started_at: tokio::time::Instant,
+}
+
+impl Default for TokioClock {
+    fn default() -> Self {
+        Self {
+            started_at: tokio::time::Instant::now(),
+        }
+    }
+}
+
+impl Clock for TokioClock {
+    fn now(&self) -> tokio::time::Duration {
+        self.started_at.elapsed()
+    }
+
+    fn sleep_until(&self, deadline: tokio::time::Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep_until(self.started_at + deadline))
+    }
+}
+
+/// Time that only moves when `ManualClock::advance` is called.
+pub struct ManualClock {
+    now: tokio::sync::watch::Sender<tokio::time::Duration>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self {
+            now: tokio::sync::watch::channel(tokio::time::Duration::ZERO).0,
+        }
+    }
+}
+
+impl ManualClock {
+    /// Moves time forward by `duration`, waking up everything sleeping until then.
+    pub fn advance(&self, duration: tokio::time::Duration) {
+        self.now.send_modify(|now| *now += duration);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> tokio::time::Duration {
+        *self.now.borrow()
+    }
+
+    fn sleep_until(&self, deadline: tokio::time::Duration) -> BoxFuture<'static, ()> {
+        let mut now = self.now.subscribe();
+
+        Box::pin(async move {
+            let _ = now.wait_for(|now| *now >= deadline).await;
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::futures::FutureExt;
+
+    use super::*;
+
+    #[rocket::async_test]
+    async fn test_manual_clock() {
+        let clock = ManualClock::default();
+        let mut sleep = clock.sleep_until(tokio::time::Duration::from_secs(2));
+
+        assert!((&mut sleep).now_or_never().is_none());
+
+        clock.advance(tokio::time::Duration::from_secs(1));
+
+        assert!((&mut sleep).now_or_never().is_none());
+
+        clock.advance(tokio::time::Duration::from_secs(1));
+
+        assert!((&mut sleep).now_or_never().is_some());
+        assert_eq!(clock.now(), tokio::time::Duration::from_secs(2));
+    }
+}
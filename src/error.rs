@@ -0,0 +1,159 @@
+use crate::events;
+
+/// What can go wrong while handling what a client sent.
+#[derive(Debug, PartialEq)]
+pub enum SkribblError {
+    /// A frame could not be decoded into an event.
+    Decode(String),
+    /// The event is valid but breaks a rule of the game, e.g., starting the game
+    /// without being the host.
+    RuleViolation {
+        code: events::ErrorCode,
+        message: String,
+    },
+    /// The state of the server is not what it should be, e.g., the room of a
+    /// connected user is gone. The connection is closed when this happens.
+    Internal(String),
+}
+
+impl SkribblError {
+    pub fn rule_violation(code: events::ErrorCode, message: impl Into<String>) -> Self {
+        Self::RuleViolation {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::Internal(message.into())
+    }
+
+    /// Returns true if the connection cannot go on after this error.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Self::Internal(_))
+    }
+
+    /// The event that tells the client what went wrong. The details of internal
+    /// errors are kept to the server.
+    pub fn to_event(&self) -> events::ServerToClientEvents {
+        match self {
+            Self::Decode(message) => events::ServerToClientEvents::Error {
+                code: events::ErrorCode::InvalidEvent,
+                message: Some(message.clone()),
+            },
+            Self::RuleViolation { code, message } => {
+                events::ServerToClientEvents::Error {
+                    code: *code,
+                    message: Some(message.clone()),
+                }
+            }
+            Self::Internal(_) => events::ServerToClientEvents::Error {
+                code: events::ErrorCode::Internal,
+                message: None,
+            },
+        }
+    }
+
+    /// The event that tells a client why it could not join a room.
+    pub fn to_connect_error(&self) -> events::ServerToClientEvents {
+        match self {
+            Self::RuleViolation { code, message } => {
+                events::ServerToClientEvents::ConnectError {
+                    code: *code,
+                    message: Some(message.clone()),
+                }
+            }
+            Self::Decode(_) | Self::Internal(_) => {
+                events::ServerToClientEvents::ConnectError {
+                    code: events::ErrorCode::Internal,
+                    message: None,
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for SkribblError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Decode(message) => write!(f, "Could not decode event: {}", message),
+            Self::RuleViolation { code, message } => write!(f, "{:?}: {}", code, message),
+            Self::Internal(message) => write!(f, "Internal error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SkribblError {}
+
+impl From<Box<dyn std::error::Error>> for SkribblError {
+    fn from(value: Box<dyn std::error::Error>) -> Self {
+        Self::Internal(value.to_string())
+    }
+}
+
+impl From<events::WebSocketMessageBuilderError> for SkribblError {
+    fn from(value: events::WebSocketMessageBuilderError) -> Self {
+        Self::Internal(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_internal_errors_are_fatal() {
+        assert!(!SkribblError::Decode("Data is too short".to_string()).is_fatal());
+        assert!(!SkribblError::rule_violation(
+            events::ErrorCode::NotHost,
+            "Only the host"
+        )
+        .is_fatal());
+        assert!(SkribblError::internal("Room not found").is_fatal());
+    }
+
+    #[test]
+    fn test_to_event() {
+        assert_eq!(
+            SkribblError::Decode("Data is too short".to_string()).to_event(),
+            events::ServerToClientEvents::Error {
+                code: events::ErrorCode::InvalidEvent,
+                message: Some("Data is too short".to_string()),
+            }
+        );
+        assert_eq!(
+            SkribblError::rule_violation(events::ErrorCode::NotHost, "Only the host")
+                .to_event(),
+            events::ServerToClientEvents::Error {
+                code: events::ErrorCode::NotHost,
+                message: Some("Only the host".to_string()),
+            }
+        );
+        assert_eq!(
+            SkribblError::internal("Room not found").to_event(),
+            events::ServerToClientEvents::Error {
+                code: events::ErrorCode::Internal,
+                message: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_to_connect_error() {
+        assert_eq!(
+            SkribblError::rule_violation(events::ErrorCode::RoomFull, "Room is full")
+                .to_connect_error(),
+            events::ServerToClientEvents::ConnectError {
+                code: events::ErrorCode::RoomFull,
+                message: Some("Room is full".to_string()),
+            }
+        );
+        assert_eq!(
+            SkribblError::internal("Room not found").to_connect_error(),
+            events::ServerToClientEvents::ConnectError {
+                code: events::ErrorCode::Internal,
+                message: None,
+            }
+        );
+    }
+}
@@ -1,6 +1,6 @@
-use crate::{state, utils, vec_with_slices};
+use crate::{binary_structs::BinaryValue, state, utils, vec_with_slices};
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Debug)]
 pub enum WebSocketMessageType {
     /// A message that is sent to everyone in a room.
     Everyone,
@@ -14,558 +14,1032 @@ pub enum WebSocketMessageType {
 pub struct WebSocketMessage {
     pub r#type: WebSocketMessageType,
     pub room_id: String,
-    pub message: ws::Message,
+    /// Encoded by the writer of every connection it is sent to, since each
+    /// connection may use a different version of the binary protocol.
+    pub event: ServerToClientEvents,
 }
 
 impl WebSocketMessage {
     pub fn send(
         self,
         server_messages: &rocket::tokio::sync::broadcast::Sender<WebSocketMessage>,
-    ) -> Result<(), rocket::tokio::sync::broadcast::error::SendError<WebSocketMessage>>
+    ) -> Result<(), Box<rocket::tokio::sync::broadcast::error::SendError<WebSocketMessage>>>
     {
-        server_messages.send(self)?;
+        server_messages.send(self).map_err(Box::new)?;
 
         Ok(())
     }
 }
 
-#[derive(Clone)]
+/// Sent as tagged JSON, e.g., `{"type":"message","message":"hi"}`, on
+/// connections that use `WireFormat::Json`.
+#[derive(
+    rocket::serde::Serialize, rocket::serde::Deserialize, Clone, PartialEq, Debug,
+)]
+#[serde(tag = "type")]
 pub enum ClientToServerEvents {
+    #[serde(rename = "startGame")]
     StartGame,
+    #[serde(rename = "pickAWord")]
     PickAWord { word: String },
+    #[serde(rename = "pointerDown")]
     PointerDown,
+    #[serde(rename = "pointerMove")]
     PointerMove { x: f64, y: f64 },
+    #[serde(rename = "pointerUp")]
     PointerUp,
+    #[serde(rename = "pointerLeave")]
     PointerLeave,
+    #[serde(rename = "changeColor")]
     ChangeColor { color: String },
+    #[serde(rename = "message")]
     Message { message: String },
+    #[serde(rename = "updateSettings")]
+    UpdateSettings { settings: state::RoomSettings },
+    #[serde(rename = "uploadCustomWords")]
+    UploadCustomWords {
+        words: String,
+        mode: state::CustomWordsMode,
+    },
+    #[serde(rename = "selectWordPack")]
+    SelectWordPack { category: String, language: String },
+    /// Many points of a stroke at once, sent instead of a `PointerMove` for each.
+    #[serde(rename = "strokeBatch")]
+    StrokeBatch { points: Vec<state::StrokePoint> },
 }
 
-impl TryFrom<&Vec<u8>> for ClientToServerEvents {
+/// The versions of the binary protocol. They differ in how the length of a field
+/// is encoded and in how structs such as rooms and users are laid out.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum BinaryProtocolVersion {
+    /// The length is a run of 255s plus a remainder that add up to it, prefixed by
+    /// how many bytes that run takes.
+    Additive = 1,
+    /// The length is an unsigned LEB128 varint.
+    Varint = 2,
+    /// The length is an unsigned LEB128 varint, and structs use the native layouts
+    /// of `binary_structs` instead of JSON.
+    Native = 3,
+}
+
+impl Default for BinaryProtocolVersion {
+    /// The version used with clients that do not state which versions they
+    /// support, i.e., `utils::consts::BINARY_PROTOCOL_VERSION`.
+    fn default() -> Self {
+        Self::Additive
+    }
+}
+
+impl TryFrom<u8> for BinaryProtocolVersion {
     type Error = Box<dyn std::error::Error>;
 
-    fn try_from(
-        value: &Vec<u8>,
-    ) -> Result<Self, <ClientToServerEvents as TryFrom<&Vec<u8>>>::Error> {
-        if value.len() < 2 {
-            return Err("Invalid message length".into());
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::Additive),
+            2 => Ok(Self::Varint),
+            3 => Ok(Self::Native),
+            _ => Err("Version mismatch".into()),
         }
+    }
+}
+
+impl From<BinaryProtocolVersion> for u8 {
+    fn from(value: BinaryProtocolVersion) -> Self {
+        value as u8
+    }
+}
+
+impl BinaryProtocolVersion {
+    /// Picks the highest version out of `client_versions` that the server supports.
+    /// Clients that do not state their versions get the default one.
+    pub fn negotiate(client_versions: &[u8]) -> Option<Self> {
+        if client_versions.is_empty() {
+            return Some(Self::default());
+        }
+
+        client_versions
+            .iter()
+            .filter_map(|version| Self::try_from(*version).ok())
+            .max()
+    }
+}
+
+/// How events are sent over a connection.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WireFormat {
+    /// Binary frames in the given version of the binary protocol.
+    Binary(BinaryProtocolVersion),
+    /// Text frames with events as tagged JSON, for debugging and for clients that
+    /// do not implement the binary protocol.
+    Json,
+}
 
-        if *value.get(0).unwrap() != utils::consts::BINARY_PROTOCOL_VERSION {
+impl Default for WireFormat {
+    fn default() -> Self {
+        Self::Binary(BinaryProtocolVersion::default())
+    }
+}
+
+/// Turns events into the bytes of the binary protocol and back.
+///
+/// Every event starts with the version of the binary protocol followed by the id
+/// of the event and then its fields, each prefixed by its length.
+pub trait BinaryCodec: Sized {
+    fn encode_with_version(
+        &self,
+        version: BinaryProtocolVersion,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
+
+    /// Decodes an event of any supported version.
+    fn decode(value: &[u8]) -> Result<Self, Box<dyn std::error::Error>>;
+
+    fn encode(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.encode_with_version(BinaryProtocolVersion::default())
+    }
+
+    /// Decodes an event, making sure it is of the version of the connection it was
+    /// received from.
+    fn decode_with_version(
+        value: &[u8],
+        version: BinaryProtocolVersion,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if value.first() != Some(&version.into()) {
             return Err("Version mismatch".into());
         }
 
-        match *value.get(1).unwrap() {
+        Self::decode(value)
+    }
+}
+
+/// Returns the version and the id of an event.
+fn get_event_header(
+    value: &[u8],
+) -> Result<(BinaryProtocolVersion, u8), Box<dyn std::error::Error>> {
+    if value.len() < 2 {
+        return Err("Invalid message length".into());
+    }
+
+    Ok((value[0].try_into()?, value[1]))
+}
+
+/// Reads the field whose length starts at `start` and returns its data along with
+/// the position right after it.
+fn get_field(
+    value: &[u8],
+    version: BinaryProtocolVersion,
+    start: usize,
+) -> Result<(&[u8], usize), Box<dyn std::error::Error>> {
+    let (length, length_end_position) = match version {
+        BinaryProtocolVersion::Additive => {
+            let length_indicator = *value.get(start).ok_or("Data is too short")?;
+            let length_start_position = start + 1;
+            let length_end_position = length_start_position + length_indicator as usize;
+            let length: usize = value
+                .get(length_start_position..length_end_position)
+                .ok_or("Data is too short")?
+                .iter()
+                .fold(0, |acc, x| acc + *x as usize);
+
+            (length, length_end_position)
+        }
+        BinaryProtocolVersion::Varint | BinaryProtocolVersion::Native => {
+            utils::read_varint(value, start)?
+        }
+    };
+
+    if length > utils::consts::MAX_PAYLOAD_SIZE {
+        return Err("Payload is too large".into());
+    }
+
+    let data_end_position = length_end_position + length;
+    let data = value
+        .get(length_end_position..data_end_position)
+        .ok_or("Data is too short")?;
+
+    Ok((data, data_end_position))
+}
+
+/// Reads a `u8` whose length starts at `start` and returns it along with the
+/// position right after it.
+fn get_u8_field(
+    value: &[u8],
+    version: BinaryProtocolVersion,
+    start: usize,
+) -> Result<(u8, usize), Box<dyn std::error::Error>> {
+    let (data, end_position) = get_field(value, version, start)?;
+    let [data] = data else {
+        return Err("Data is invalid".into());
+    };
+
+    Ok((*data, end_position))
+}
+
+/// Reads a big endian `u16` whose length starts at `start` and returns it along
+/// with the position right after it.
+fn get_u16_field(
+    value: &[u8],
+    version: BinaryProtocolVersion,
+    start: usize,
+) -> Result<(u16, usize), Box<dyn std::error::Error>> {
+    let (data, end_position) = get_field(value, version, start)?;
+    let data = u16::from_be_bytes(data.try_into().ok().ok_or("Data is invalid")?);
+
+    Ok((data, end_position))
+}
+
+/// Reads a big endian `f64` whose length starts at `start` and returns it along
+/// with the position right after it.
+fn get_f64_field(
+    value: &[u8],
+    version: BinaryProtocolVersion,
+    start: usize,
+) -> Result<(f64, usize), Box<dyn std::error::Error>> {
+    let (data, end_position) = get_field(value, version, start)?;
+    let data = f64::from_be_bytes(data.try_into().ok().ok_or("Data is invalid")?);
+
+    Ok((data, end_position))
+}
+
+/// Reads a `String` whose length starts at `start` and returns it along with the
+/// position right after it.
+fn get_string_field(
+    value: &[u8],
+    version: BinaryProtocolVersion,
+    start: usize,
+) -> Result<(String, usize), Box<dyn std::error::Error>> {
+    let (data, end_position) = get_field(value, version, start)?;
+
+    Ok((String::from_utf8(data.to_vec())?, end_position))
+}
+
+/// Reads a struct whose length starts at `start` and returns it along with the
+/// position right after it. Versions before `Native` send structs as JSON.
+fn get_struct_field<T: rocket::serde::de::DeserializeOwned + BinaryValue>(
+    value: &[u8],
+    version: BinaryProtocolVersion,
+    start: usize,
+) -> Result<(T, usize), Box<dyn std::error::Error>> {
+    let (data, end_position) = get_field(value, version, start)?;
+    let data = match version {
+        BinaryProtocolVersion::Native => T::from_binary(data)?,
+        _ => serde_json::from_slice(data)?,
+    };
+
+    Ok((data, end_position))
+}
+
+/// Reads the code of an error and the message that may come after it.
+fn get_error_fields(
+    value: &[u8],
+    version: BinaryProtocolVersion,
+) -> Result<(ErrorCode, Option<String>), Box<dyn std::error::Error>> {
+    let (code, code_end_position) = get_u8_field(value, version, 2)?;
+    let message = if code_end_position < value.len() {
+        Some(get_string_field(value, version, code_end_position)?.0)
+    } else {
+        None
+    };
+
+    Ok((code.try_into()?, message))
+}
+
+/// Reads the points of a stroke whose length starts at `start` and returns them
+/// along with the position right after it.
+fn get_stroke_points_field(
+    value: &[u8],
+    version: BinaryProtocolVersion,
+    start: usize,
+) -> Result<(Vec<state::StrokePoint>, usize), Box<dyn std::error::Error>> {
+    let (data, end_position) = get_field(value, version, start)?;
+
+    Ok((utils::decode_stroke_points(data)?, end_position))
+}
+
+fn get_room_settings_field(
+    value: &[u8],
+    version: BinaryProtocolVersion,
+    start: usize,
+) -> Result<(state::RoomSettings, usize), Box<dyn std::error::Error>> {
+    let (max_rounds, max_rounds_end_position) = get_u8_field(value, version, start)?;
+    let (max_users, max_users_end_position) =
+        get_u8_field(value, version, max_rounds_end_position)?;
+    let (draw_time_limit, draw_time_limit_end_position) =
+        get_u8_field(value, version, max_users_end_position)?;
+    let (pick_word_time_limit, pick_word_time_limit_end_position) =
+        get_u8_field(value, version, draw_time_limit_end_position)?;
+    let (amount_of_words_to_pick, end_position) =
+        get_u8_field(value, version, pick_word_time_limit_end_position)?;
+
+    Ok((
+        state::RoomSettings {
+            max_rounds,
+            max_users,
+            draw_time_limit,
+            pick_word_time_limit,
+            amount_of_words_to_pick,
+        },
+        end_position,
+    ))
+}
+
+/// Prefixes `data` with its length so it can be read with `get_field`.
+fn field_to_vec_of_u8(
+    data: &[u8],
+    version: BinaryProtocolVersion,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if data.len() > utils::consts::MAX_PAYLOAD_SIZE {
+        return Err("Payload is too large".into());
+    }
+
+    match version {
+        BinaryProtocolVersion::Additive => {
+            let length = utils::turn_usize_to_vec_of_u8(data.len());
+            let length_indicator: u8 = length.len().try_into()?;
+
+            Ok(vec_with_slices!(length_indicator; &length, data))
+        }
+        BinaryProtocolVersion::Varint | BinaryProtocolVersion::Native => {
+            let mut bytes = utils::turn_usize_to_varint(data.len());
+
+            bytes.extend_from_slice(data);
+
+            Ok(bytes)
+        }
+    }
+}
+
+fn struct_field_to_vec_of_u8(
+    value: &(impl rocket::serde::Serialize + BinaryValue),
+    version: BinaryProtocolVersion,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let data = match version {
+        BinaryProtocolVersion::Native => value.to_binary()?,
+        _ => serde_json::to_vec(value)?,
+    };
+
+    field_to_vec_of_u8(&data, version)
+}
+
+fn room_settings_to_vec_of_u8(
+    settings: &state::RoomSettings,
+    version: BinaryProtocolVersion,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut bytes = vec![];
+
+    for value in [
+        settings.max_rounds,
+        settings.max_users,
+        settings.draw_time_limit,
+        settings.pick_word_time_limit,
+        settings.amount_of_words_to_pick,
+    ] {
+        bytes.extend(field_to_vec_of_u8(&[value], version)?);
+    }
+
+    Ok(bytes)
+}
+
+impl ClientToServerEvents {
+    /// Reads an event from a frame sent over a connection that uses `format`.
+    pub fn from_message(
+        message: &ws::Message,
+        format: WireFormat,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        match (message, format) {
+            (ws::Message::Binary(data), WireFormat::Binary(version)) => {
+                Self::decode_with_version(data, version)
+            }
+            (ws::Message::Text(text), WireFormat::Json) => {
+                Ok(serde_json::from_str(text)?)
+            }
+            (ws::Message::Text(_), WireFormat::Binary(_)) => {
+                Err("Expected a binary frame".into())
+            }
+            (ws::Message::Binary(_), WireFormat::Json) => {
+                Err("Expected a text frame".into())
+            }
+            _ => Err("Unsupported frame".into()),
+        }
+    }
+}
+
+impl BinaryCodec for ClientToServerEvents {
+    fn encode_with_version(
+        &self,
+        version: BinaryProtocolVersion,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut bytes = vec![version.into(), self.into()];
+
+        match self {
+            Self::StartGame
+            | Self::PointerDown
+            | Self::PointerUp
+            | Self::PointerLeave => {}
+            Self::PickAWord { word } => {
+                bytes.extend(field_to_vec_of_u8(word.as_bytes(), version)?)
+            }
+            Self::PointerMove { x, y } => {
+                bytes.extend(field_to_vec_of_u8(&x.to_be_bytes(), version)?);
+                bytes.extend(field_to_vec_of_u8(&y.to_be_bytes(), version)?);
+            }
+            Self::ChangeColor { color } => {
+                bytes.extend(field_to_vec_of_u8(color.as_bytes(), version)?)
+            }
+            Self::Message { message } => {
+                bytes.extend(field_to_vec_of_u8(message.as_bytes(), version)?)
+            }
+            Self::UpdateSettings { settings } => {
+                bytes.extend(room_settings_to_vec_of_u8(settings, version)?)
+            }
+            Self::UploadCustomWords { words, mode } => {
+                bytes.extend(field_to_vec_of_u8(&[(*mode).into()], version)?);
+                bytes.extend(field_to_vec_of_u8(words.as_bytes(), version)?);
+            }
+            Self::SelectWordPack { category, language } => {
+                bytes.extend(field_to_vec_of_u8(category.as_bytes(), version)?);
+                bytes.extend(field_to_vec_of_u8(language.as_bytes(), version)?);
+            }
+            Self::StrokeBatch { points } => bytes.extend(field_to_vec_of_u8(
+                &utils::encode_stroke_points(points),
+                version,
+            )?),
+        }
+
+        Ok(bytes)
+    }
+
+    fn decode(value: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let (version, event_id) = get_event_header(value)?;
+
+        match event_id {
             0 => Ok(Self::StartGame),
             1 => {
-                let length_of_word_length_indicator =
-                    *value.get(2).ok_or("Data is too short")?;
-                let length_of_word_start_position = 3;
-                let length_of_word_end_position = length_of_word_start_position
-                    + length_of_word_length_indicator as usize;
-                let length_of_word: usize = value
-                    .get(length_of_word_start_position..length_of_word_end_position)
-                    .ok_or("Data is too short")?
-                    .iter()
-                    .fold(0, |acc, x| acc + *x as usize);
-                let word_end_position = length_of_word_end_position + length_of_word;
-                let word = String::from_utf8(
-                    value
-                        .get(length_of_word_end_position..word_end_position)
-                        .ok_or("Data is too short")?
-                        .to_vec(),
-                )?;
+                let (word, _) = get_string_field(value, version, 2)?;
 
                 Ok(Self::PickAWord { word })
             }
             2 => Ok(Self::PointerDown),
             3 => {
-                let length_of_x_length_indicator =
-                    *value.get(2).ok_or("Data is too short")?;
-                let length_of_x_end_position = 3 + length_of_x_length_indicator as usize;
-                let length_of_x: usize = value
-                    .get(3..length_of_x_end_position)
-                    .ok_or("Data is too short")?
-                    .iter()
-                    .fold(0, |acc, x| acc + *x as usize);
-                let x_end_position = length_of_x_end_position + length_of_x;
-                let x = f64::from_be_bytes(
-                    value
-                        .get(length_of_x_end_position..x_end_position)
-                        .ok_or("Data is too short")?
-                        .try_into()
-                        .ok()
-                        .ok_or("Data is invalid")?,
-                );
-
-                let length_of_y_length_indicator = *value
-                    .get(x_end_position)
-                    .ok_or("Data is too short")?;
-                let length_of_y_start_position = x_end_position + 1;
-                let length_of_y_end_position =
-                    length_of_y_start_position + length_of_y_length_indicator as usize;
-                let length_of_y: usize = value
-                    .get(length_of_y_start_position..length_of_y_end_position)
-                    .ok_or("Data is too short")?
-                    .iter()
-                    .fold(0, |acc, x| acc + *x as usize);
-                let y_end_position = length_of_y_end_position + length_of_y;
-                let y = f64::from_be_bytes(
-                    value
-                        .get(length_of_y_end_position..y_end_position)
-                        .ok_or("Data is too short")?
-                        .try_into()
-                        .ok()
-                        .ok_or("Data is invalid")?,
-                );
+                let (x, x_end_position) = get_f64_field(value, version, 2)?;
+                let (y, _) = get_f64_field(value, version, x_end_position)?;
 
                 Ok(Self::PointerMove { x, y })
             }
             4 => Ok(Self::PointerUp),
             5 => Ok(Self::PointerLeave),
             6 => {
-                let length_of_color_length_indicator =
-                    *value.get(2).ok_or("Data is too short")?;
-                let length_of_color_start_position = 3;
-                let length_of_color_end_position = length_of_color_start_position
-                    + length_of_color_length_indicator as usize;
-                let length_of_color: usize = value
-                    .get(length_of_color_start_position..length_of_color_end_position)
-                    .ok_or("Data is too short")?
-                    .iter()
-                    .fold(0, |acc, x| acc + *x as usize);
-                let color_end_position = length_of_color_end_position + length_of_color;
-                let color = String::from_utf8(
-                    value
-                        .get(length_of_color_end_position..color_end_position)
-                        .ok_or("Data is too short")?
-                        .to_vec(),
-                )?;
+                let (color, _) = get_string_field(value, version, 2)?;
 
                 Ok(Self::ChangeColor { color })
             }
             7 => {
-                let length_of_message_length_indicator =
-                    *value.get(2).ok_or("Data is too short")?;
-                let length_of_message_start_position = 3;
-                let length_of_message_end_position = length_of_message_start_position
-                    + length_of_message_length_indicator as usize;
-                let length_of_message: usize = value
-                    .get(length_of_message_start_position..length_of_message_end_position)
-                    .ok_or("Data is too short")?
-                    .iter()
-                    .fold(0, |acc, x| acc + *x as usize);
-                let message_end_position =
-                    length_of_message_end_position + length_of_message;
-                let message = String::from_utf8(
-                    value
-                        .get(length_of_message_end_position..message_end_position)
-                        .ok_or("Data is too short")?
-                        .to_vec(),
-                )?;
+                let (message, _) = get_string_field(value, version, 2)?;
 
                 Ok(Self::Message { message })
             }
+            8 => {
+                let (settings, _) = get_room_settings_field(value, version, 2)?;
+
+                Ok(Self::UpdateSettings { settings })
+            }
+            9 => {
+                let (mode, mode_end_position) = get_u8_field(value, version, 2)?;
+                let (words, _) = get_string_field(value, version, mode_end_position)?;
+
+                Ok(Self::UploadCustomWords {
+                    words,
+                    mode: mode.try_into()?,
+                })
+            }
+            10 => {
+                let (category, category_end_position) =
+                    get_string_field(value, version, 2)?;
+                let (language, _) =
+                    get_string_field(value, version, category_end_position)?;
+
+                Ok(Self::SelectWordPack { category, language })
+            }
+            11 => Ok(Self::StrokeBatch {
+                points: get_stroke_points_field(value, version, 2)?.0,
+            }),
             _ => Err("Invalid event type".into()),
         }
     }
 }
 
-#[derive(Clone)]
+impl TryFrom<&Vec<u8>> for ClientToServerEvents {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(
+        value: &Vec<u8>,
+    ) -> Result<Self, <ClientToServerEvents as TryFrom<&Vec<u8>>>::Error> {
+        Self::decode(value)
+    }
+}
+
+impl TryFrom<ClientToServerEvents> for Vec<u8> {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(value: ClientToServerEvents) -> Result<Self, Self::Error> {
+        value.encode()
+    }
+}
+
+// We borrow since we only need to see what variant is being converted.
+impl From<&ClientToServerEvents> for u8 {
+    fn from(value: &ClientToServerEvents) -> Self {
+        match value {
+            ClientToServerEvents::StartGame => 0,
+            ClientToServerEvents::PickAWord { .. } => 1,
+            ClientToServerEvents::PointerDown => 2,
+            ClientToServerEvents::PointerMove { .. } => 3,
+            ClientToServerEvents::PointerUp => 4,
+            ClientToServerEvents::PointerLeave => 5,
+            ClientToServerEvents::ChangeColor { .. } => 6,
+            ClientToServerEvents::Message { .. } => 7,
+            ClientToServerEvents::UpdateSettings { .. } => 8,
+            ClientToServerEvents::UploadCustomWords { .. } => 9,
+            ClientToServerEvents::SelectWordPack { .. } => 10,
+            ClientToServerEvents::StrokeBatch { .. } => 11,
+        }
+    }
+}
+
+/// Why something a client did was rejected, so clients can react to it and show
+/// it in their own language. The values are sent as is and must never change.
+#[derive(
+    rocket::serde::Serialize,
+    rocket::serde::Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Debug,
+)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum ErrorCode {
+    Internal = 0,
+    /// The event could not be decoded.
+    InvalidEvent = 1,
+    NotHost = 2,
+    NotEnoughPlayers = 3,
+    GameAlreadyStarted = 4,
+    /// The event is not allowed in the current state of the room.
+    InvalidRoomState = 5,
+    InvalidSettings = 6,
+    InvalidCustomWords = 7,
+    RoomNotPrivate = 8,
+    WordPackNotFound = 9,
+    /// Spectators can only chat.
+    NotAllowedForSpectators = 10,
+    /// A user who guessed the word tried to send it in the chat.
+    WordExposed = 11,
+    RoomNotFound = 12,
+    /// The room is no longer accepting players, e.g., the game has finished.
+    RoomNotAvailable = 13,
+    RoomFull = 14,
+    SessionExpired = 15,
+    UnsupportedProtocolVersion = 16,
+    /// The query parameters of `/ws` are invalid.
+    InvalidParams = 17,
+    /// Only the user drawing can do this.
+    NotYourTurn = 18,
+    /// The picked word is not one of the words to pick.
+    InvalidWord = 19,
+}
+
+impl TryFrom<u8> for ErrorCode {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Internal),
+            1 => Ok(Self::InvalidEvent),
+            2 => Ok(Self::NotHost),
+            3 => Ok(Self::NotEnoughPlayers),
+            4 => Ok(Self::GameAlreadyStarted),
+            5 => Ok(Self::InvalidRoomState),
+            6 => Ok(Self::InvalidSettings),
+            7 => Ok(Self::InvalidCustomWords),
+            8 => Ok(Self::RoomNotPrivate),
+            9 => Ok(Self::WordPackNotFound),
+            10 => Ok(Self::NotAllowedForSpectators),
+            11 => Ok(Self::WordExposed),
+            12 => Ok(Self::RoomNotFound),
+            13 => Ok(Self::RoomNotAvailable),
+            14 => Ok(Self::RoomFull),
+            15 => Ok(Self::SessionExpired),
+            16 => Ok(Self::UnsupportedProtocolVersion),
+            17 => Ok(Self::InvalidParams),
+            18 => Ok(Self::NotYourTurn),
+            19 => Ok(Self::InvalidWord),
+            _ => Err("Invalid error code".into()),
+        }
+    }
+}
+
+impl From<ErrorCode> for u8 {
+    fn from(value: ErrorCode) -> Self {
+        value as u8
+    }
+}
+
+/// Sent as tagged JSON, e.g., `{"type":"message","message":"hi"}`, on
+/// connections that use `WireFormat::Json`.
+#[derive(
+    rocket::serde::Serialize, rocket::serde::Deserialize, Clone, PartialEq, Debug,
+)]
+#[serde(tag = "type")]
 pub enum ServerToClientEvents {
+    #[serde(rename = "error")]
     Error {
-        message: String,
+        code: ErrorCode,
+        message: Option<String>,
     },
+    /// Sent right before the connection is closed.
+    #[serde(rename = "connectError")]
     ConnectError {
-        message: String,
-    },
-    UserJoined {
-        user: state::User,
+        code: ErrorCode,
+        message: Option<String>,
     },
+    #[serde(rename = "userJoined")]
+    UserJoined { user: state::User },
+    #[serde(rename = "userLeft")]
     UserLeft {
+        #[serde(rename = "userId")]
         user_id: String,
     },
+    #[serde(rename = "startGame")]
     StartGame,
+    #[serde(rename = "pickAWord")]
     PickAWord {
-        words_to_pick: [String; 3],
+        #[serde(rename = "wordsToPick")]
+        words_to_pick: Vec<String>,
     },
+    #[serde(rename = "endGame")]
     EndGame,
+    #[serde(rename = "resetRoom")]
     ResetRoom,
+    #[serde(rename = "newTurn")]
     NewTurn {
+        #[serde(rename = "userIdToDraw")]
         user_id_to_draw: String,
     },
-    NewWord {
-        word: String,
-    },
+    #[serde(rename = "newWord")]
+    NewWord { word: String },
+    #[serde(rename = "newHost")]
     NewHost {
+        #[serde(rename = "userId")]
         user_id: String,
     },
-    NewRound {
-        round: u8,
-    },
+    #[serde(rename = "newRound")]
+    NewRound { round: u8 },
+    #[serde(rename = "pointerDown")]
     PointerDown,
-    PointerMove {
-        x: f64,
-        y: f64,
-    },
+    #[serde(rename = "pointerMove")]
+    PointerMove { x: f64, y: f64 },
+    #[serde(rename = "pointerUp")]
     PointerUp,
+    #[serde(rename = "pointerLeave")]
     PointerLeave,
-    ChangeColor {
-        color: String,
-    },
+    #[serde(rename = "changeColor")]
+    ChangeColor { color: String },
+    #[serde(rename = "sendGameState")]
     SendGameState {
-        room: state::Room,
+        room: Box<state::Room>,
         user: state::User,
+        #[serde(rename = "usersInRoom")]
         users_in_room: Vec<state::User>,
+        #[serde(rename = "resumeToken")]
+        resume_token: String,
     },
+    #[serde(rename = "message")]
     Message {
+        #[serde(rename = "userId")]
         user_id: String,
         message: String,
     },
+    #[serde(rename = "addScore")]
     AddScore {
+        #[serde(rename = "userId")]
         user_id: String,
         score: u16,
     },
+    #[serde(rename = "tick")]
     Tick {
+        #[serde(rename = "timeLeft")]
         time_left: u8,
     },
+    #[serde(rename = "userGuessed")]
     UserGuessed {
+        #[serde(rename = "userId")]
         user_id: String,
     },
-    SystemMessage {
-        message: String,
-    },
-    RevealWord {
-        word: String,
-    },
+    #[serde(rename = "systemMessage")]
+    SystemMessage { message: String },
+    #[serde(rename = "revealWord")]
+    RevealWord { word: String },
+    /// The word being drawn with some of its letters revealed.
+    #[serde(rename = "hint")]
+    Hint { word: String },
+    /// Sent only to the user whose guess is close to the word being drawn.
+    #[serde(rename = "closeGuess")]
+    CloseGuess { guess: String },
+    #[serde(rename = "settingsUpdated")]
+    SettingsUpdated { settings: state::RoomSettings },
+    /// Everything drawn so far in the current turn, for users who join mid-turn.
+    #[serde(rename = "canvasSnapshot")]
+    CanvasSnapshot { actions: Vec<state::CanvasAction> },
+    /// The points of a stroke relayed every `utils::consts::STROKE_BATCH_INTERVAL`
+    /// milliseconds.
+    #[serde(rename = "strokeBatch")]
+    StrokeBatch { points: Vec<state::StrokePoint> },
 }
 
-impl TryFrom<ServerToClientEvents> for Vec<u8> {
-    type Error = Box<dyn std::error::Error>;
+impl ServerToClientEvents {
+    pub fn to_message(
+        &self,
+        format: WireFormat,
+    ) -> Result<ws::Message, Box<dyn std::error::Error>> {
+        match format {
+            WireFormat::Binary(version) => {
+                Ok(ws::Message::Binary(self.encode_with_version(version)?))
+            }
+            WireFormat::Json => Ok(ws::Message::Text(serde_json::to_string(self)?)),
+        }
+    }
 
-    fn try_from(value: ServerToClientEvents) -> Result<Self, Self::Error> {
-        let event_as_borrowed = &value;
-
-        match event_as_borrowed {
-            ServerToClientEvents::Error { message }
-            | ServerToClientEvents::ConnectError { message } => {
-                let message_as_bytes = message.as_bytes();
-                let length_of_message =
-                    utils::turn_usize_to_vec_of_u8(message_as_bytes.len());
-                let length_of_message_length_indicator = length_of_message.len();
-
-                Ok(vec_with_slices![
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into(),
-                    length_of_message_length_indicator.try_into()?;
-                    &length_of_message,
-                    message_as_bytes
-                ])
-            }
-            ServerToClientEvents::UserJoined { user } => {
-                let stringified_user_as_bytes = serde_json::to_vec(&user)?;
-                let length_of_stringified_user =
-                    utils::turn_usize_to_vec_of_u8(stringified_user_as_bytes.len());
-                let length_of_stringified_user_length_indicator =
-                    length_of_stringified_user.len();
-
-                Ok(vec_with_slices!(
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into(),
-                    length_of_stringified_user_length_indicator.try_into()?;
-                    &length_of_stringified_user,
-                    &stringified_user_as_bytes
-                ))
-            }
-            ServerToClientEvents::UserLeft { user_id } => {
-                let user_id_as_bytes = user_id.as_bytes();
-                let length_of_user_id =
-                    utils::turn_usize_to_vec_of_u8(user_id_as_bytes.len());
-                let length_of_user_id_length_indicator = length_of_user_id.len();
-
-                Ok(vec_with_slices!(
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into(),
-                    length_of_user_id_length_indicator.try_into()?;
-                    &length_of_user_id,
-                    user_id_as_bytes
-                ))
-            }
-            ServerToClientEvents::StartGame => Ok(vec![
-                utils::consts::BINARY_PROTOCOL_VERSION,
-                event_as_borrowed.into(),
-            ]),
-            ServerToClientEvents::PickAWord { words_to_pick } => {
-                let stringified_words_to_pick_as_bytes =
-                    serde_json::to_vec(&words_to_pick)?;
-                let length_of_stringified_words_to_pick = utils::turn_usize_to_vec_of_u8(
-                    stringified_words_to_pick_as_bytes.len(),
-                );
-                let length_of_stringified_words_to_pick_length_indicator =
-                    length_of_stringified_words_to_pick.len();
-
-                Ok(vec_with_slices!(
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into(),
-                    length_of_stringified_words_to_pick_length_indicator.try_into()?;
-                    &length_of_stringified_words_to_pick,
-                    &stringified_words_to_pick_as_bytes
-                ))
-            }
-            ServerToClientEvents::EndGame => Ok(vec![
-                utils::consts::BINARY_PROTOCOL_VERSION,
-                event_as_borrowed.into(),
-            ]),
-            ServerToClientEvents::ResetRoom => Ok(vec![
-                utils::consts::BINARY_PROTOCOL_VERSION,
-                event_as_borrowed.into(),
-            ]),
-            ServerToClientEvents::NewTurn { user_id_to_draw } => {
-                let user_id_to_draw_as_bytes = user_id_to_draw.as_bytes();
-                let length_of_user_id_to_draw =
-                    utils::turn_usize_to_vec_of_u8(user_id_to_draw_as_bytes.len());
-                let length_of_user_id_to_draw_length_indicator =
-                    length_of_user_id_to_draw.len();
-
-                Ok(vec_with_slices!(
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into(),
-                    length_of_user_id_to_draw_length_indicator.try_into()?;
-                    &length_of_user_id_to_draw,
-                    user_id_to_draw_as_bytes
-                ))
-            }
-            ServerToClientEvents::NewWord { word } => {
-                let word_as_bytes = word.as_bytes();
-                let length_of_word = utils::turn_usize_to_vec_of_u8(word_as_bytes.len());
-                let length_of_word_length_indicator = length_of_word.len();
-
-                Ok(vec_with_slices!(
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into(),
-                    length_of_word_length_indicator.try_into()?;
-                    &length_of_word,
-                    word_as_bytes
-                ))
-            }
-            ServerToClientEvents::NewRound { round } => {
-                let round_as_bytes = round.to_be_bytes();
-                let length_of_round =
-                    utils::turn_usize_to_vec_of_u8(round_as_bytes.len());
-                let length_of_round_length_indicator = length_of_round.len();
-
-                Ok(vec_with_slices!(
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into(),
-                    length_of_round_length_indicator.try_into()?;
-                    &length_of_round,
-                    &round_as_bytes
-                ))
-            }
-            ServerToClientEvents::NewHost { user_id } => {
-                let user_id_as_bytes = user_id.as_bytes();
-                let length_of_user_id =
-                    utils::turn_usize_to_vec_of_u8(user_id_as_bytes.len());
-                let length_of_user_id_length_indicator = length_of_user_id.len();
-
-                Ok(vec_with_slices!(
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into(),
-                    length_of_user_id_length_indicator.try_into()?;
-                    &length_of_user_id,
-                    user_id_as_bytes
-                ))
-            }
-            ServerToClientEvents::PointerDown => Ok(vec![
-                utils::consts::BINARY_PROTOCOL_VERSION,
-                event_as_borrowed.into(),
-            ]),
-            ServerToClientEvents::PointerMove { x, y } => {
-                let x_as_bytes = x.to_be_bytes();
-                let length_of_x = utils::turn_usize_to_vec_of_u8(x_as_bytes.len());
-                let length_of_x_length_indicator = length_of_x.len();
-
-                let y_as_bytes = y.to_be_bytes();
-                let length_of_y = utils::turn_usize_to_vec_of_u8(y_as_bytes.len());
-                let length_of_y_length_indicator = length_of_y.len();
-
-                Ok(vec_with_slices!(
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into();
-                    vec_with_slices!(
-                        length_of_x_length_indicator.try_into()?;
-                        &length_of_x,
-                        &x_as_bytes
-                    ).as_slice(),
-                    vec_with_slices!(
-                        length_of_y_length_indicator.try_into()?;
-                        &length_of_y,
-                        &y_as_bytes
-                    ).as_slice()
-                ))
-            }
-            ServerToClientEvents::PointerUp | ServerToClientEvents::PointerLeave => {
-                Ok(vec![
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into(),
-                ])
-            }
-            ServerToClientEvents::ChangeColor { color } => {
-                let color_as_bytes = color.as_bytes();
-                let length_of_color =
-                    utils::turn_usize_to_vec_of_u8(color_as_bytes.len());
-                let length_of_color_length_indicator = length_of_color.len();
-
-                Ok(vec_with_slices!(
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into(),
-                    length_of_color_length_indicator.try_into()?;
-                    &length_of_color,
-                    color_as_bytes
-                ))
+    /// Splits `actions` into as few `CanvasSnapshot` events as needed for each of
+    /// them to fit in a single field. Actions that would not fit on their own are
+    /// left out.
+    pub fn canvas_snapshots(actions: &[state::CanvasAction]) -> Vec<Self> {
+        // Accounts for the brackets of the JSON array.
+        const EMPTY_CHUNK_LENGTH: usize = 2;
+
+        let mut snapshots = vec![];
+        let mut chunk = vec![];
+        let mut chunk_length = EMPTY_CHUNK_LENGTH;
+
+        for action in actions {
+            // Accounts for the comma that separates it from the previous action.
+            let Ok(action_length) = serde_json::to_vec(action).map(|a| a.len() + 1)
+            else {
+                continue;
+            };
+
+            if EMPTY_CHUNK_LENGTH + action_length > utils::consts::MAX_FIELD_LENGTH {
+                continue;
             }
-            ServerToClientEvents::SendGameState {
+
+            if chunk_length + action_length > utils::consts::MAX_FIELD_LENGTH {
+                snapshots.push(Self::CanvasSnapshot {
+                    actions: std::mem::take(&mut chunk),
+                });
+                chunk_length = EMPTY_CHUNK_LENGTH;
+            }
+
+            chunk.push(action.clone());
+            chunk_length += action_length;
+        }
+
+        if !chunk.is_empty() {
+            snapshots.push(Self::CanvasSnapshot { actions: chunk });
+        }
+
+        snapshots
+    }
+}
+
+impl From<state::CanvasAction> for ServerToClientEvents {
+    fn from(value: state::CanvasAction) -> Self {
+        match value {
+            state::CanvasAction::PointerDown => Self::PointerDown,
+            state::CanvasAction::PointerMove { x, y } => Self::PointerMove { x, y },
+            state::CanvasAction::PointerUp => Self::PointerUp,
+            state::CanvasAction::PointerLeave => Self::PointerLeave,
+            state::CanvasAction::ChangeColor { color } => Self::ChangeColor { color },
+            state::CanvasAction::StrokeBatch { points } => Self::StrokeBatch { points },
+        }
+    }
+}
+
+impl BinaryCodec for ServerToClientEvents {
+    fn encode_with_version(
+        &self,
+        version: BinaryProtocolVersion,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut bytes = vec![version.into(), self.into()];
+
+        match self {
+            Self::StartGame
+            | Self::EndGame
+            | Self::ResetRoom
+            | Self::PointerDown
+            | Self::PointerUp
+            | Self::PointerLeave => {}
+            Self::Error { code, message } | Self::ConnectError { code, message } => {
+                bytes.extend(field_to_vec_of_u8(&[(*code).into()], version)?);
+
+                if let Some(message) = message {
+                    bytes.extend(field_to_vec_of_u8(message.as_bytes(), version)?);
+                }
+            }
+            Self::SystemMessage { message } | Self::CloseGuess { guess: message } => {
+                bytes.extend(field_to_vec_of_u8(message.as_bytes(), version)?)
+            }
+            Self::UserLeft { user_id }
+            | Self::NewHost { user_id }
+            | Self::UserGuessed { user_id }
+            | Self::NewTurn {
+                user_id_to_draw: user_id,
+            } => bytes.extend(field_to_vec_of_u8(user_id.as_bytes(), version)?),
+            Self::NewWord { word } | Self::RevealWord { word } | Self::Hint { word } => {
+                bytes.extend(field_to_vec_of_u8(word.as_bytes(), version)?)
+            }
+            Self::ChangeColor { color } => {
+                bytes.extend(field_to_vec_of_u8(color.as_bytes(), version)?)
+            }
+            Self::UserJoined { user } => {
+                bytes.extend(struct_field_to_vec_of_u8(user, version)?)
+            }
+            Self::PickAWord { words_to_pick } => {
+                bytes.extend(struct_field_to_vec_of_u8(words_to_pick, version)?)
+            }
+            Self::CanvasSnapshot { actions } => {
+                bytes.extend(struct_field_to_vec_of_u8(actions, version)?)
+            }
+            Self::NewRound { round } => {
+                bytes.extend(field_to_vec_of_u8(&[*round], version)?)
+            }
+            Self::Tick { time_left } => {
+                bytes.extend(field_to_vec_of_u8(&[*time_left], version)?)
+            }
+            Self::PointerMove { x, y } => {
+                bytes.extend(field_to_vec_of_u8(&x.to_be_bytes(), version)?);
+                bytes.extend(field_to_vec_of_u8(&y.to_be_bytes(), version)?);
+            }
+            Self::SendGameState {
                 room,
                 user,
                 users_in_room,
+                resume_token,
             } => {
-                let room_as_bytes = serde_json::to_vec(room)?;
-                let length_of_room = utils::turn_usize_to_vec_of_u8(room_as_bytes.len());
-                let length_of_room_length_indicator = length_of_room.len();
-
-                let user_as_bytes = serde_json::to_vec(user)?;
-                let length_of_user = utils::turn_usize_to_vec_of_u8(user_as_bytes.len());
-                let length_of_user_length_indicator = length_of_user.len();
-
-                let users_in_room_as_bytes = serde_json::to_vec(users_in_room)?;
-                let length_of_users_in_room =
-                    utils::turn_usize_to_vec_of_u8(users_in_room_as_bytes.len());
-                let length_of_users_in_room_length_indicator =
-                    length_of_users_in_room.len();
-
-                Ok(vec_with_slices![
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into();
-                    vec_with_slices!(
-                        length_of_room_length_indicator.try_into()?;
-                        &length_of_room,
-                        &room_as_bytes
-                    ).as_slice(),
-                    vec_with_slices!(
-                        length_of_user_length_indicator.try_into()?;
-                        &length_of_user,
-                        &user_as_bytes
-                    ).as_slice(),
-                    vec_with_slices!(
-                        length_of_users_in_room_length_indicator.try_into()?;
-                        &length_of_users_in_room,
-                        &users_in_room_as_bytes
-                    ).as_slice()
-                ])
-            }
-            ServerToClientEvents::Message { user_id, message } => {
-                let user_id_as_bytes = user_id.as_bytes();
-                let length_of_user_id =
-                    utils::turn_usize_to_vec_of_u8(user_id_as_bytes.len());
-                let length_of_user_id_length_indicator = length_of_user_id.len();
-
-                let message_as_bytes = message.as_bytes();
-                let length_of_message =
-                    utils::turn_usize_to_vec_of_u8(message_as_bytes.len());
-                let length_of_message_length_indicator = length_of_message.len();
-
-                Ok(vec_with_slices!(
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into();
-                    vec_with_slices!(
-                        length_of_user_id_length_indicator.try_into()?;
-                        &length_of_user_id,
-                        user_id_as_bytes
-                    ).as_slice(),
-                    vec_with_slices!(
-                        length_of_message_length_indicator.try_into()?;
-                        &length_of_message,
-                        message_as_bytes
-                    ).as_slice()
-                ))
-            }
-            ServerToClientEvents::SystemMessage { message } => {
-                let message_as_bytes = message.as_bytes();
-                let length_of_message =
-                    utils::turn_usize_to_vec_of_u8(message_as_bytes.len());
-                let length_of_message_length_indicator = length_of_message.len();
-
-                Ok(vec_with_slices!(
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into(),
-                    length_of_message_length_indicator.try_into()?;
-                    &length_of_message,
-                    message_as_bytes
-                ))
-            }
-            ServerToClientEvents::AddScore { user_id, score } => {
-                let user_id_as_bytes = user_id.as_bytes();
-                let length_of_user_id =
-                    utils::turn_usize_to_vec_of_u8(user_id_as_bytes.len());
-                let length_of_user_id_length_indicator = length_of_user_id.len();
-
-                let score_as_bytes = score.to_be_bytes();
-                let score_length = utils::turn_usize_to_vec_of_u8(score_as_bytes.len());
-                let length_of_score_length_indicator = score_length.len();
-
-                Ok(vec_with_slices!(
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into();
-                    vec_with_slices!(
-                        length_of_user_id_length_indicator.try_into()?;
-                        &length_of_user_id,
-                        user_id_as_bytes
-                    ).as_slice(),
-                    vec_with_slices!(
-                        length_of_score_length_indicator.try_into()?;
-                        &score_length,
-                        &score_as_bytes
-                    ).as_slice()
-                ))
-            }
-            ServerToClientEvents::Tick { time_left } => Ok(vec![
-                utils::consts::BINARY_PROTOCOL_VERSION,
-                event_as_borrowed.into(),
-                1,
-                1,
-                time_left.clone(),
-            ]),
-            ServerToClientEvents::UserGuessed { user_id } => {
-                let user_id_as_bytes = user_id.as_bytes();
-                let user_id_length =
-                    utils::turn_usize_to_vec_of_u8(user_id_as_bytes.len());
-                let length_of_user_id_length_indicator = user_id_length.len();
+                bytes.extend(struct_field_to_vec_of_u8(room, version)?);
+                bytes.extend(struct_field_to_vec_of_u8(user, version)?);
+                bytes.extend(struct_field_to_vec_of_u8(users_in_room, version)?);
+                bytes.extend(field_to_vec_of_u8(resume_token.as_bytes(), version)?);
+            }
+            Self::Message { user_id, message } => {
+                bytes.extend(field_to_vec_of_u8(user_id.as_bytes(), version)?);
+                bytes.extend(field_to_vec_of_u8(message.as_bytes(), version)?);
+            }
+            Self::AddScore { user_id, score } => {
+                bytes.extend(field_to_vec_of_u8(user_id.as_bytes(), version)?);
+                bytes.extend(field_to_vec_of_u8(&score.to_be_bytes(), version)?);
+            }
+            Self::SettingsUpdated { settings } => {
+                bytes.extend(room_settings_to_vec_of_u8(settings, version)?)
+            }
+            Self::StrokeBatch { points } => bytes.extend(field_to_vec_of_u8(
+                &utils::encode_stroke_points(points),
+                version,
+            )?),
+        }
+
+        Ok(bytes)
+    }
 
-                Ok(vec_with_slices!(
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into(),
-                    length_of_user_id_length_indicator as u8;
-                    &user_id_length,
-                    user_id_as_bytes
-                ))
+    fn decode(value: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let (version, event_id) = get_event_header(value)?;
+
+        match event_id {
+            0 => {
+                let (code, message) = get_error_fields(value, version)?;
+
+                Ok(Self::Error { code, message })
+            }
+            1 => {
+                let (code, message) = get_error_fields(value, version)?;
+
+                Ok(Self::ConnectError { code, message })
             }
-            ServerToClientEvents::RevealWord { word } => {
-                let word_as_bytes = word.as_bytes();
-                let word_length = utils::turn_usize_to_vec_of_u8(word_as_bytes.len());
-                let length_of_word_length_indicator = word_length.len();
+            2 => Ok(Self::UserJoined {
+                user: get_struct_field(value, version, 2)?.0,
+            }),
+            3 => Ok(Self::UserLeft {
+                user_id: get_string_field(value, version, 2)?.0,
+            }),
+            4 => Ok(Self::StartGame),
+            5 => Ok(Self::PickAWord {
+                words_to_pick: get_struct_field(value, version, 2)?.0,
+            }),
+            6 => Ok(Self::EndGame),
+            7 => Ok(Self::ResetRoom),
+            8 => Ok(Self::NewTurn {
+                user_id_to_draw: get_string_field(value, version, 2)?.0,
+            }),
+            9 => Ok(Self::NewWord {
+                word: get_string_field(value, version, 2)?.0,
+            }),
+            10 => Ok(Self::NewRound {
+                round: get_u8_field(value, version, 2)?.0,
+            }),
+            11 => Ok(Self::NewHost {
+                user_id: get_string_field(value, version, 2)?.0,
+            }),
+            12 => Ok(Self::PointerDown),
+            13 => {
+                let (x, x_end_position) = get_f64_field(value, version, 2)?;
+                let (y, _) = get_f64_field(value, version, x_end_position)?;
 
-                Ok(vec_with_slices!(
-                    utils::consts::BINARY_PROTOCOL_VERSION,
-                    event_as_borrowed.into(),
-                    length_of_word_length_indicator as u8;
-                    &word_length,
-                    word_as_bytes
-                ))
+                Ok(Self::PointerMove { x, y })
             }
+            14 => Ok(Self::PointerUp),
+            15 => Ok(Self::PointerLeave),
+            16 => Ok(Self::ChangeColor {
+                color: get_string_field(value, version, 2)?.0,
+            }),
+            17 => {
+                let (room, room_end_position) = get_struct_field(value, version, 2)?;
+                let (user, user_end_position) =
+                    get_struct_field(value, version, room_end_position)?;
+                let (users_in_room, users_in_room_end_position) =
+                    get_struct_field(value, version, user_end_position)?;
+                let (resume_token, _) =
+                    get_string_field(value, version, users_in_room_end_position)?;
+
+                Ok(Self::SendGameState {
+                    room: Box::new(room),
+                    user,
+                    users_in_room,
+                    resume_token,
+                })
+            }
+            18 => {
+                let (user_id, user_id_end_position) =
+                    get_string_field(value, version, 2)?;
+                let (message, _) =
+                    get_string_field(value, version, user_id_end_position)?;
+
+                Ok(Self::Message { user_id, message })
+            }
+            19 => {
+                let (user_id, user_id_end_position) =
+                    get_string_field(value, version, 2)?;
+                let (score, _) = get_u16_field(value, version, user_id_end_position)?;
+
+                Ok(Self::AddScore { user_id, score })
+            }
+            20 => Ok(Self::Tick {
+                time_left: get_u8_field(value, version, 2)?.0,
+            }),
+            21 => Ok(Self::UserGuessed {
+                user_id: get_string_field(value, version, 2)?.0,
+            }),
+            22 => Ok(Self::SystemMessage {
+                message: get_string_field(value, version, 2)?.0,
+            }),
+            23 => Ok(Self::RevealWord {
+                word: get_string_field(value, version, 2)?.0,
+            }),
+            24 => Ok(Self::Hint {
+                word: get_string_field(value, version, 2)?.0,
+            }),
+            25 => Ok(Self::CloseGuess {
+                guess: get_string_field(value, version, 2)?.0,
+            }),
+            26 => Ok(Self::SettingsUpdated {
+                settings: get_room_settings_field(value, version, 2)?.0,
+            }),
+            27 => Ok(Self::CanvasSnapshot {
+                actions: get_struct_field(value, version, 2)?.0,
+            }),
+            28 => Ok(Self::StrokeBatch {
+                points: get_stroke_points_field(value, version, 2)?.0,
+            }),
+            _ => Err("Invalid event type".into()),
         }
     }
 }
 
+impl TryFrom<ServerToClientEvents> for Vec<u8> {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(value: ServerToClientEvents) -> Result<Self, Self::Error> {
+        value.encode()
+    }
+}
+
+impl TryFrom<&Vec<u8>> for ServerToClientEvents {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(
+        value: &Vec<u8>,
+    ) -> Result<Self, <ServerToClientEvents as TryFrom<&Vec<u8>>>::Error> {
+        Self::decode(value)
+    }
+}
+
 // We borrow since we only need to see what variant is being converted.
 impl From<&ServerToClientEvents> for u8 {
     fn from(value: &ServerToClientEvents) -> Self {
@@ -594,6 +1068,716 @@ impl From<&ServerToClientEvents> for u8 {
             ServerToClientEvents::UserGuessed { .. } => 21,
             ServerToClientEvents::SystemMessage { .. } => 22,
             ServerToClientEvents::RevealWord { .. } => 23,
+            ServerToClientEvents::Hint { .. } => 24,
+            ServerToClientEvents::CloseGuess { .. } => 25,
+            ServerToClientEvents::SettingsUpdated { .. } => 26,
+            ServerToClientEvents::CanvasSnapshot { .. } => 27,
+            ServerToClientEvents::StrokeBatch { .. } => 28,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const VERSIONS: [BinaryProtocolVersion; 3] = [
+        BinaryProtocolVersion::Additive,
+        BinaryProtocolVersion::Varint,
+        BinaryProtocolVersion::Native,
+    ];
+
+    fn test_user() -> state::User {
+        state::UserBuilder::default()
+            .id("user".to_string())
+            .display_name("user".to_string())
+            .room_id("room".to_string())
+            .score(120)
+            .build()
+            .unwrap()
+    }
+
+    fn test_settings() -> state::RoomSettings {
+        state::RoomSettings {
+            max_rounds: 3,
+            max_users: 8,
+            draw_time_limit: 80,
+            pick_word_time_limit: 15,
+            amount_of_words_to_pick: 3,
+        }
+    }
+
+    /// Fields that are never sent, e.g., the room of a user, cannot survive a round
+    /// trip, so we check that decoding and encoding again gives the same bytes.
+    fn assert_server_event_round_trip(event: ServerToClientEvents) {
+        for version in VERSIONS {
+            let bytes = event.encode_with_version(version).unwrap();
+            let decoded = ServerToClientEvents::decode(&bytes).unwrap();
+
+            assert_eq!(decoded.encode_with_version(version).unwrap(), bytes);
+            assert_eq!(u8::from(&decoded), u8::from(&event));
+        }
+
+        let ws::Message::Text(text) = event.to_message(WireFormat::Json).unwrap() else {
+            panic!("Expected a text frame");
+        };
+        let decoded: ServerToClientEvents = serde_json::from_str(&text).unwrap();
+
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), text);
+    }
+
+    #[test]
+    fn test_client_events_round_trip() {
+        let events = vec![
+            ClientToServerEvents::StartGame,
+            ClientToServerEvents::PickAWord {
+                word: "crème brûlée".to_string(),
+            },
+            ClientToServerEvents::PointerDown,
+            ClientToServerEvents::PointerMove { x: 0.25, y: -12.5 },
+            ClientToServerEvents::PointerUp,
+            ClientToServerEvents::PointerLeave,
+            ClientToServerEvents::ChangeColor {
+                color: "#ff0000".to_string(),
+            },
+            ClientToServerEvents::Message {
+                message: "a".repeat(1000),
+            },
+            ClientToServerEvents::Message {
+                message: String::new(),
+            },
+            ClientToServerEvents::UpdateSettings {
+                settings: test_settings(),
+            },
+            ClientToServerEvents::UploadCustomWords {
+                words: "apple, pear".to_string(),
+                mode: state::CustomWordsMode::Replace,
+            },
+            ClientToServerEvents::SelectWordPack {
+                category: "animals".to_string(),
+                language: "es".to_string(),
+            },
+            ClientToServerEvents::StrokeBatch {
+                points: vec![
+                    state::StrokePoint { x: 0, y: 65535 },
+                    state::StrokePoint { x: 300, y: 65000 },
+                    state::StrokePoint { x: 65535, y: 0 },
+                ],
+            },
+            ClientToServerEvents::StrokeBatch { points: vec![] },
+        ];
+
+        for event in events {
+            for version in VERSIONS {
+                let bytes = event.encode_with_version(version).unwrap();
+
+                assert_eq!(bytes[0], u8::from(version));
+                assert_eq!(ClientToServerEvents::decode(&bytes).unwrap(), event);
+            }
+
+            let message = ws::Message::Text(serde_json::to_string(&event).unwrap());
+
+            assert_eq!(
+                ClientToServerEvents::from_message(&message, WireFormat::Json).unwrap(),
+                event
+            );
+        }
+    }
+
+    #[test]
+    fn test_json_matches_the_wire_format() {
+        let message = ws::Message::Text(
+            r#"{"type":"uploadCustomWords","words":"apple","mode":"replace"}"#
+                .to_string(),
+        );
+
+        assert_eq!(
+            ClientToServerEvents::from_message(&message, WireFormat::Json).unwrap(),
+            ClientToServerEvents::UploadCustomWords {
+                words: "apple".to_string(),
+                mode: state::CustomWordsMode::Replace,
+            }
+        );
+        assert_eq!(
+            ServerToClientEvents::NewTurn {
+                user_id_to_draw: "user".to_string(),
+            }
+            .to_message(WireFormat::Json)
+            .unwrap(),
+            ws::Message::Text(r#"{"type":"newTurn","userIdToDraw":"user"}"#.to_string())
+        );
+    }
+
+    #[test]
+    fn test_frames_must_match_the_wire_format() {
+        let binary =
+            ws::Message::Binary(ClientToServerEvents::StartGame.encode().unwrap());
+        let text = ws::Message::Text(r#"{"type":"startGame"}"#.to_string());
+
+        assert!(
+            ClientToServerEvents::from_message(&binary, WireFormat::default()).is_ok()
+        );
+        assert!(ClientToServerEvents::from_message(&text, WireFormat::Json).is_ok());
+        assert!(ClientToServerEvents::from_message(&binary, WireFormat::Json).is_err());
+        assert!(
+            ClientToServerEvents::from_message(&text, WireFormat::default()).is_err()
+        );
+        assert!(ClientToServerEvents::from_message(
+            &ws::Message::Text(r#"{"type":"unknown"}"#.to_string()),
+            WireFormat::Json
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_server_events_round_trip() {
+        let room = state::RoomBuilder::default()
+            .id("room".to_string())
+            .host_id("user".to_string())
+            .state(state::RoomState::Playing {
+                playing_state: state::PlayingState::Drawing {
+                    current_word: "*****".to_string(),
+                    time_left: 30,
+                    revealed_indices: vec![],
+                },
+                current_user_id: "user".to_string(),
+                current_round: 2,
+            })
+            .build()
+            .unwrap();
+        let events = vec![
+            ServerToClientEvents::Error {
+                code: ErrorCode::Internal,
+                message: Some("error".to_string()),
+            },
+            ServerToClientEvents::Error {
+                code: ErrorCode::NotHost,
+                message: None,
+            },
+            ServerToClientEvents::ConnectError {
+                code: ErrorCode::RoomFull,
+                message: Some("Room is full".to_string()),
+            },
+            ServerToClientEvents::UserJoined { user: test_user() },
+            ServerToClientEvents::UserLeft {
+                user_id: "user".to_string(),
+            },
+            ServerToClientEvents::StartGame,
+            ServerToClientEvents::PickAWord {
+                words_to_pick: vec!["apple".to_string(), "pear".to_string()],
+            },
+            ServerToClientEvents::EndGame,
+            ServerToClientEvents::ResetRoom,
+            ServerToClientEvents::NewTurn {
+                user_id_to_draw: "user".to_string(),
+            },
+            ServerToClientEvents::NewWord {
+                word: "*****".to_string(),
+            },
+            ServerToClientEvents::NewRound { round: 2 },
+            ServerToClientEvents::NewHost {
+                user_id: "user".to_string(),
+            },
+            ServerToClientEvents::PointerDown,
+            ServerToClientEvents::PointerMove { x: 1.5, y: 2.75 },
+            ServerToClientEvents::PointerUp,
+            ServerToClientEvents::PointerLeave,
+            ServerToClientEvents::ChangeColor {
+                color: "#00ff00".to_string(),
+            },
+            ServerToClientEvents::SendGameState {
+                room: Box::new(room),
+                user: test_user(),
+                users_in_room: vec![test_user()],
+                resume_token: "token".to_string(),
+            },
+            ServerToClientEvents::Message {
+                user_id: "user".to_string(),
+                message: "hello".to_string(),
+            },
+            ServerToClientEvents::AddScore {
+                user_id: "user".to_string(),
+                score: 300,
+            },
+            ServerToClientEvents::Tick { time_left: 42 },
+            ServerToClientEvents::UserGuessed {
+                user_id: "user".to_string(),
+            },
+            ServerToClientEvents::SystemMessage {
+                message: "user has reconnected".to_string(),
+            },
+            ServerToClientEvents::RevealWord {
+                word: "apple".to_string(),
+            },
+            ServerToClientEvents::Hint {
+                word: "a****".to_string(),
+            },
+            ServerToClientEvents::CloseGuess {
+                guess: "aple".to_string(),
+            },
+            ServerToClientEvents::SettingsUpdated {
+                settings: test_settings(),
+            },
+            ServerToClientEvents::CanvasSnapshot {
+                actions: vec![
+                    state::CanvasAction::ChangeColor {
+                        color: "#000000".to_string(),
+                    },
+                    state::CanvasAction::PointerDown,
+                    state::CanvasAction::PointerMove { x: 0.5, y: 0.5 },
+                    state::CanvasAction::PointerUp,
+                    state::CanvasAction::StrokeBatch {
+                        points: vec![state::StrokePoint { x: 1, y: 2 }],
+                    },
+                ],
+            },
+            ServerToClientEvents::StrokeBatch {
+                points: vec![
+                    state::StrokePoint { x: 32768, y: 100 },
+                    state::StrokePoint { x: 32770, y: 98 },
+                ],
+            },
+        ];
+
+        for event in events {
+            assert_server_event_round_trip(event);
+        }
+    }
+
+    #[test]
+    fn test_server_events_without_hidden_fields_decode_to_the_same_event() {
+        let events = vec![
+            ServerToClientEvents::AddScore {
+                user_id: "user".to_string(),
+                score: 300,
+            },
+            ServerToClientEvents::PointerMove { x: 1.5, y: 2.75 },
+            ServerToClientEvents::SettingsUpdated {
+                settings: test_settings(),
+            },
+        ];
+
+        for event in events {
+            let bytes = event.encode().unwrap();
+
+            assert_eq!(ServerToClientEvents::decode(&bytes).unwrap(), event);
+        }
+    }
+
+    #[test]
+    fn test_native_version_does_not_embed_json() {
+        let event = ServerToClientEvents::UserJoined { user: test_user() };
+        let varint_bytes = event
+            .encode_with_version(BinaryProtocolVersion::Varint)
+            .unwrap();
+        let native_bytes = event
+            .encode_with_version(BinaryProtocolVersion::Native)
+            .unwrap();
+
+        assert_eq!(varint_bytes[3], b'{');
+        assert_eq!(native_bytes[3], crate::binary_structs::user_tags::ID);
+        assert!(native_bytes.len() < varint_bytes.len());
+    }
+
+    #[test]
+    fn test_errors_without_a_message_decode_to_the_same_event() {
+        let event = ServerToClientEvents::Error {
+            code: ErrorCode::NotHost,
+            message: None,
+        };
+        let bytes = event.encode().unwrap();
+
+        assert_eq!(
+            bytes,
+            vec![utils::consts::BINARY_PROTOCOL_VERSION, 0, 1, 1, 2]
+        );
+        assert_eq!(ServerToClientEvents::decode(&bytes).unwrap(), event);
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"type":"error","code":2,"message":null}"#
+        );
+    }
+
+    #[test]
+    fn test_error_codes_are_stable() {
+        for value in 0..=19 {
+            assert_eq!(u8::from(ErrorCode::try_from(value).unwrap()), value);
+        }
+
+        assert_eq!(u8::from(ErrorCode::NotHost), 2);
+        assert_eq!(u8::from(ErrorCode::InvalidParams), 17);
+        assert_eq!(u8::from(ErrorCode::InvalidWord), 19);
+        assert!(ErrorCode::try_from(20).is_err());
+    }
+
+    #[test]
+    fn test_encoding_matches_the_wire_format() {
+        assert_eq!(
+            ServerToClientEvents::Tick { time_left: 42 }
+                .encode()
+                .unwrap(),
+            vec![
+                utils::consts::BINARY_PROTOCOL_VERSION,
+                20,
+                1,
+                1,
+                42
+            ]
+        );
+
+        let message = "a".repeat(300);
+        let bytes = ClientToServerEvents::Message {
+            message: message.clone(),
+        }
+        .encode()
+        .unwrap();
+
+        assert_eq!(
+            bytes[..5],
+            [
+                utils::consts::BINARY_PROTOCOL_VERSION,
+                7,
+                2,
+                255,
+                45
+            ]
+        );
+        assert_eq!(&bytes[5..], message.as_bytes());
+
+        let bytes = ClientToServerEvents::Message {
+            message: message.clone(),
+        }
+        .encode_with_version(BinaryProtocolVersion::Varint)
+        .unwrap();
+
+        assert_eq!(bytes[..4], [2, 7, 0xac, 2]);
+        assert_eq!(&bytes[4..], message.as_bytes());
+    }
+
+    #[test]
+    fn test_stroke_batches_take_4_bytes_per_point() {
+        let points = vec![state::StrokePoint { x: 100, y: 200 }; 100];
+        let bytes = ServerToClientEvents::StrokeBatch { points }
+            .encode_with_version(BinaryProtocolVersion::Varint)
+            .unwrap();
+
+        assert_eq!(bytes.len(), 2 + 2 + 100 * 4);
+    }
+
+    #[test]
+    fn test_default_version_is_the_one_the_server_sends() {
+        assert_eq!(
+            u8::from(BinaryProtocolVersion::default()),
+            utils::consts::BINARY_PROTOCOL_VERSION
+        );
+        assert_eq!(
+            BinaryProtocolVersion::try_from(utils::consts::MIN_BINARY_PROTOCOL_VERSION)
+                .unwrap(),
+            BinaryProtocolVersion::Additive
+        );
+        assert_eq!(
+            BinaryProtocolVersion::try_from(
+                utils::consts::LATEST_BINARY_PROTOCOL_VERSION
+            )
+            .unwrap(),
+            BinaryProtocolVersion::Native
+        );
+    }
+
+    #[test]
+    fn test_negotiate_version() {
+        assert_eq!(
+            BinaryProtocolVersion::negotiate(&[]),
+            Some(BinaryProtocolVersion::default())
+        );
+        assert_eq!(
+            BinaryProtocolVersion::negotiate(&[1]),
+            Some(BinaryProtocolVersion::Additive)
+        );
+        assert_eq!(
+            BinaryProtocolVersion::negotiate(&[2, 1]),
+            Some(BinaryProtocolVersion::Varint)
+        );
+        assert_eq!(
+            BinaryProtocolVersion::negotiate(&[1, 2, 9]),
+            Some(BinaryProtocolVersion::Varint)
+        );
+        assert_eq!(
+            BinaryProtocolVersion::negotiate(&[3, 1]),
+            Some(BinaryProtocolVersion::Native)
+        );
+        assert_eq!(BinaryProtocolVersion::negotiate(&[0, 9]), None);
+    }
+
+    #[test]
+    fn test_decode_with_version() {
+        let bytes = ClientToServerEvents::StartGame
+            .encode_with_version(BinaryProtocolVersion::Varint)
+            .unwrap();
+
+        assert!(ClientToServerEvents::decode_with_version(
+            &bytes,
+            BinaryProtocolVersion::Varint
+        )
+        .is_ok());
+        assert!(ClientToServerEvents::decode_with_version(
+            &bytes,
+            BinaryProtocolVersion::Additive
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_payload_size_is_enforced() {
+        let message = "a".repeat(utils::consts::MAX_PAYLOAD_SIZE + 1);
+
+        assert!(ClientToServerEvents::Message { message }
+            .encode_with_version(BinaryProtocolVersion::Varint)
+            .is_err());
+
+        let mut bytes = vec![2, 7];
+
+        bytes.extend(utils::turn_usize_to_varint(
+            utils::consts::MAX_PAYLOAD_SIZE + 1,
+        ));
+        bytes.extend(vec![b'a'; utils::consts::MAX_PAYLOAD_SIZE + 1]);
+
+        assert_eq!(
+            ClientToServerEvents::decode(&bytes)
+                .unwrap_err()
+                .to_string(),
+            "Payload is too large"
+        );
+
+        // Too large lengths are rejected before checking there is enough data.
+        let mut bytes = vec![2, 7];
+
+        bytes.extend(utils::turn_usize_to_varint(usize::MAX));
+
+        assert_eq!(
+            ClientToServerEvents::decode(&bytes)
+                .unwrap_err()
+                .to_string(),
+            "Payload is too large"
+        );
+    }
+
+    #[test]
+    fn test_decoding_invalid_events_fails() {
+        assert!(ClientToServerEvents::decode(&[]).is_err());
+        assert!(ClientToServerEvents::decode(&[0, 0]).is_err());
+        assert!(ClientToServerEvents::decode(&[4, 0]).is_err());
+        assert!(ClientToServerEvents::decode(&[2, 7, 0x80]).is_err());
+        assert!(ClientToServerEvents::decode(&[
+            utils::consts::BINARY_PROTOCOL_VERSION,
+            200
+        ])
+        .is_err());
+        assert!(ClientToServerEvents::decode(&[
+            utils::consts::BINARY_PROTOCOL_VERSION,
+            7,
+            1,
+            10,
+            b'a'
+        ])
+        .is_err());
+        assert!(ServerToClientEvents::decode(&[
+            utils::consts::BINARY_PROTOCOL_VERSION,
+            20,
+            1,
+            2,
+            1,
+            2
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_canvas_snapshots_fit_in_a_field() {
+        let actions = (0..10_000)
+            .map(|i| state::CanvasAction::PointerMove {
+                x: i as f64 / 3.0,
+                y: i as f64 / 7.0,
+            })
+            .collect::<Vec<state::CanvasAction>>();
+        let snapshots = ServerToClientEvents::canvas_snapshots(&actions);
+
+        assert!(snapshots.len() > 1);
+
+        let mut replayed = vec![];
+
+        for snapshot in snapshots {
+            let ServerToClientEvents::CanvasSnapshot { actions } = snapshot.clone()
+            else {
+                panic!("Expected a `CanvasSnapshot`");
+            };
+
+            replayed.extend(actions);
+
+            assert!(Vec::<u8>::try_from(snapshot).is_ok());
+        }
+
+        assert_eq!(replayed, actions);
+    }
+
+    #[test]
+    fn test_canvas_snapshots_skip_actions_too_big_for_a_field() {
+        let actions = vec![
+            state::CanvasAction::PointerDown,
+            state::CanvasAction::ChangeColor {
+                color: "a".repeat(utils::consts::MAX_FIELD_LENGTH),
+            },
+            state::CanvasAction::PointerUp,
+        ];
+        let snapshots = ServerToClientEvents::canvas_snapshots(&actions);
+
+        assert_eq!(snapshots.len(), 1);
+        assert!(matches!(
+            &snapshots[0],
+            ServerToClientEvents::CanvasSnapshot { actions } if actions.len() == 2
+        ));
+    }
+
+    #[test]
+    fn test_no_canvas_snapshots_for_an_empty_canvas() {
+        assert!(ServerToClientEvents::canvas_snapshots(&[]).is_empty());
+    }
+
+    /// Inputs in `fuzz/regressions` once made the decoder misbehave or are edge
+    /// cases it has to keep handling, so they are replayed on every test run.
+    #[test]
+    fn test_fuzz_regressions_never_panic() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fuzz/regressions/client_to_server_events");
+        let mut count = 0;
+
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let bytes = std::fs::read(entry.unwrap().path()).unwrap();
+
+            let _ = ClientToServerEvents::try_from(&bytes);
+            count += 1;
+        }
+
+        assert!(count > 0);
+    }
+
+    fn version_strategy() -> impl Strategy<Value = BinaryProtocolVersion> {
+        prop::sample::select(VERSIONS.to_vec())
+    }
+
+    fn client_event_strategy() -> impl Strategy<Value = ClientToServerEvents> {
+        let settings = (
+            any::<u8>(),
+            any::<u8>(),
+            any::<u8>(),
+            any::<u8>(),
+            any::<u8>(),
+        )
+            .prop_map(|(a, b, c, d, e)| state::RoomSettings {
+                max_rounds: a,
+                max_users: b,
+                draw_time_limit: c,
+                pick_word_time_limit: d,
+                amount_of_words_to_pick: e,
+            });
+        let points = prop::collection::vec(
+            (any::<u16>(), any::<u16>()).prop_map(|(x, y)| state::StrokePoint { x, y }),
+            0..64,
+        );
+
+        prop_oneof![
+            Just(ClientToServerEvents::StartGame),
+            any::<String>().prop_map(|word| ClientToServerEvents::PickAWord { word }),
+            Just(ClientToServerEvents::PointerDown),
+            (any::<f64>(), any::<f64>())
+                .prop_filter("NaN is not equal to itself", |(x, y)| {
+                    !x.is_nan() && !y.is_nan()
+                })
+                .prop_map(|(x, y)| ClientToServerEvents::PointerMove { x, y }),
+            Just(ClientToServerEvents::PointerUp),
+            Just(ClientToServerEvents::PointerLeave),
+            any::<String>().prop_map(|color| ClientToServerEvents::ChangeColor { color }),
+            any::<String>().prop_map(|message| ClientToServerEvents::Message { message }),
+            settings
+                .prop_map(|settings| ClientToServerEvents::UpdateSettings { settings }),
+            (any::<String>(), any::<bool>()).prop_map(|(words, replace)| {
+                ClientToServerEvents::UploadCustomWords {
+                    words,
+                    mode: if replace {
+                        state::CustomWordsMode::Replace
+                    } else {
+                        state::CustomWordsMode::Mix
+                    },
+                }
+            }),
+            (any::<String>(), any::<String>()).prop_map(|(category, language)| {
+                ClientToServerEvents::SelectWordPack { category, language }
+            }),
+            points.prop_map(|points| ClientToServerEvents::StrokeBatch { points }),
+        ]
+    }
+
+    /// Bytes that start like an event so decoding gets past the header.
+    fn event_like_bytes() -> impl Strategy<Value = Vec<u8>> {
+        (
+            0u8..=4,
+            0u8..=30,
+            prop::collection::vec(any::<u8>(), 0..512),
+        )
+            .prop_map(|(version, id, body)| vec_with_slices!(version, id; &body))
+    }
+
+    proptest! {
+        #[test]
+        fn prop_decoding_arbitrary_bytes_never_panics(
+            bytes in prop::collection::vec(any::<u8>(), 0..1024),
+        ) {
+            let _ = ClientToServerEvents::try_from(&bytes);
+            let _ = ServerToClientEvents::try_from(&bytes);
+        }
+
+        #[test]
+        fn prop_decoding_event_like_bytes_never_panics(bytes in event_like_bytes()) {
+            let _ = ClientToServerEvents::try_from(&bytes);
+            let _ = ServerToClientEvents::try_from(&bytes);
+        }
+
+        #[test]
+        fn prop_client_events_round_trip(
+            event in client_event_strategy(),
+            version in version_strategy(),
+        ) {
+            let bytes = event.encode_with_version(version).unwrap();
+
+            prop_assert_eq!(ClientToServerEvents::try_from(&bytes).unwrap(), event);
+        }
+
+        /// Truncating or corrupting a valid event must fail cleanly, and whatever
+        /// still decodes must not be bigger than what was sent.
+        #[test]
+        fn prop_decoding_corrupted_events_never_panics(
+            event in client_event_strategy(),
+            version in version_strategy(),
+            index in any::<prop::sample::Index>(),
+            byte in any::<u8>(),
+            truncate in any::<bool>(),
+        ) {
+            let mut bytes = event.encode_with_version(version).unwrap();
+            let index = index.index(bytes.len());
+
+            if truncate {
+                bytes.truncate(index);
+            } else {
+                bytes[index] = byte;
+            }
+
+            if let Ok(decoded) = ClientToServerEvents::try_from(&bytes) {
+                let reencoded = decoded.encode_with_version(version);
+
+                prop_assert!(reencoded.map_or(true, |reencoded| reencoded.len() <= bytes.len() * 2));
+            }
         }
     }
 }
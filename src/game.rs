@@ -0,0 +1,2533 @@
+//! The rules of the game. A room changes only through `Room::apply`, which
+//! returns what should happen as a result, e.g., the events to send, and leaves
+//! doing so to whoever runs the room.
+
+use rand::Rng;
+
+use crate::error::SkribblError;
+use crate::{events, scoring, similarity, state, utils, word_packs};
+
+#[derive(Clone, Debug)]
+pub enum Command {
+    /// An event sent by the user with the id `user_id`.
+    Event {
+        user_id: String,
+        event: events::ClientToServerEvents,
+    },
+    /// A second of the current turn has passed.
+    Tick,
+    /// Adds a user to the room. Players are only let in while waiting for
+    /// players if `only_if_waiting` is true, i.e., through matchmaking.
+    Join {
+        user: state::User,
+        only_if_waiting: bool,
+    },
+    /// Removes a user from the room for good, passing the turn or the host to
+    /// someone else if needed.
+    Leave { user_id: String },
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Effect {
+    Send {
+        to: events::WebSocketMessageType,
+        event: events::ServerToClientEvents,
+    },
+    /// Starts counting down the current turn, with the first tick right away.
+    StartTimer,
+    StopTimer,
+}
+
+/// A room along with everyone in it, players and spectators alike.
+#[derive(Clone, Debug)]
+pub struct Room {
+    pub room: state::Room,
+    pub users: Vec<state::User>,
+    word_packs: word_packs::WordPacks,
+}
+
+impl Room {
+    /// A new room with its host, who `state::Room::amount_of_users` already
+    /// counts. The host can pick any of `word_packs`.
+    pub fn new(
+        room: state::Room,
+        host: state::User,
+        word_packs: word_packs::WordPacks,
+    ) -> Self {
+        Self {
+            room,
+            users: vec![host],
+            word_packs,
+        }
+    }
+
+    pub fn user(&self, user_id: &str) -> Option<&state::User> {
+        self.users.iter().find(|user| user.id == user_id)
+    }
+
+    pub fn user_mut(&mut self, user_id: &str) -> Option<&mut state::User> {
+        self.users
+            .iter_mut()
+            .find(|user| user.id == user_id)
+    }
+
+    /// The users in the room who are not spectators.
+    pub fn players(&self) -> Vec<state::User> {
+        self.users
+            .iter()
+            .filter(|user| !user.is_spectator)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns true if a player can join the room through matchmaking.
+    pub fn is_available(&self) -> bool {
+        self.room.state == state::RoomState::Waiting
+            && self.room.amount_of_users < self.room.max_users
+    }
+
+    /// Adds `user` to the room, counting them in `state::Room::amount_of_users`
+    /// unless they are a spectator.
+    pub fn add_user(&mut self, user: state::User) {
+        if !user.is_spectator {
+            self.room.amount_of_users += 1;
+        }
+
+        self.users.push(user);
+    }
+
+    pub fn remove_user(&mut self, user_id: &str) -> Option<state::User> {
+        let user_idx = self
+            .users
+            .iter()
+            .position(|user| user.id == user_id)?;
+        let user = self.users.remove(user_idx);
+
+        if !user.is_spectator {
+            self.room.amount_of_users -= 1;
+        }
+
+        Some(user)
+    }
+
+    /// The state of the room as `user` is allowed to see it, followed by whatever
+    /// they need to catch up with the current turn.
+    pub fn catch_up_events(
+        &self,
+        user: &state::User,
+    ) -> Vec<events::ServerToClientEvents> {
+        let mut catch_up_events = vec![events::ServerToClientEvents::SendGameState {
+            room: Box::new(self.room.redacted_for(user)),
+            user: user.clone(),
+            resume_token: user.resume_token.clone(),
+            users_in_room: self.players(),
+        }];
+
+        catch_up_events.extend(current_turn_events(&self.room, user));
+
+        catch_up_events
+    }
+
+    /// Applies `command` to the room. Rule violations are returned as errors, in
+    /// which case nothing should be done.
+    pub fn apply(&mut self, command: Command) -> Result<Vec<Effect>, SkribblError> {
+        let mut effects = vec![];
+
+        match command {
+            Command::Event { user_id, event } => {
+                self.on_event(&user_id, event, &mut effects)?
+            }
+            Command::Tick => {
+                let Self { room, users, .. } = self;
+
+                on_tick(&room.id.clone(), room, users, &mut effects)?;
+            }
+            Command::Join {
+                user,
+                only_if_waiting,
+            } => self.join(user, only_if_waiting, &mut effects)?,
+            Command::Leave { user_id } => self.leave(&user_id, &mut effects)?,
+        }
+
+        Ok(effects)
+    }
+
+    fn on_event(
+        &mut self,
+        user_id: &str,
+        event: events::ClientToServerEvents,
+        effects: &mut Vec<Effect>,
+    ) -> Result<(), SkribblError> {
+        let Self {
+            room,
+            users,
+            word_packs,
+        } = self;
+        let room_id = &room.id.clone();
+
+        match event {
+            events::ClientToServerEvents::StartGame => {
+                start_game_event(room_id, user_id, room, users, effects)
+            }
+            events::ClientToServerEvents::PickAWord { word } => {
+                on_pick_a_word(word, user_id, room, effects)
+            }
+            events::ClientToServerEvents::PointerDown => {
+                on_canvas_action(state::CanvasAction::PointerDown, user_id, room, effects)
+            }
+            events::ClientToServerEvents::PointerMove { x, y } => on_canvas_action(
+                state::CanvasAction::PointerMove { x, y },
+                user_id,
+                room,
+                effects,
+            ),
+            events::ClientToServerEvents::PointerUp => {
+                on_canvas_action(state::CanvasAction::PointerUp, user_id, room, effects)
+            }
+            events::ClientToServerEvents::PointerLeave => on_canvas_action(
+                state::CanvasAction::PointerLeave,
+                user_id,
+                room,
+                effects,
+            ),
+            events::ClientToServerEvents::ChangeColor { color } => on_canvas_action(
+                state::CanvasAction::ChangeColor { color },
+                user_id,
+                room,
+                effects,
+            ),
+            events::ClientToServerEvents::StrokeBatch { points } => on_canvas_action(
+                state::CanvasAction::StrokeBatch { points },
+                user_id,
+                room,
+                effects,
+            ),
+            events::ClientToServerEvents::UpdateSettings { settings } => {
+                on_update_settings(settings, user_id, room, effects)
+            }
+            events::ClientToServerEvents::UploadCustomWords { words, mode } => {
+                on_upload_custom_words(&words, mode, user_id, room, effects)
+            }
+            events::ClientToServerEvents::SelectWordPack { category, language } => {
+                on_select_word_pack(
+                    &category, &language, user_id, room, effects, word_packs,
+                )
+            }
+            events::ClientToServerEvents::Message { message } => {
+                on_message(message, room_id, user_id, room, users, effects)
+            }
+        }
+    }
+
+    fn join(
+        &mut self,
+        user: state::User,
+        only_if_waiting: bool,
+        effects: &mut Vec<Effect>,
+    ) -> Result<(), SkribblError> {
+        if !user.is_spectator {
+            // Users can join a game that is being played, but not one that has
+            // already finished.
+            if self.room.state == state::RoomState::Finished
+                || (only_if_waiting && !self.is_available())
+            {
+                return Err(SkribblError::rule_violation(
+                    events::ErrorCode::RoomNotAvailable,
+                    "Room is not available",
+                ));
+            }
+
+            if self.room.amount_of_users >= self.room.max_users {
+                return Err(SkribblError::rule_violation(
+                    events::ErrorCode::RoomFull,
+                    "Room is full",
+                ));
+            }
+
+            // Spectators only watch, so no one is told about them.
+            effects.push(Effect::Send {
+                to: events::WebSocketMessageType::Broadcast {
+                    sender_id: user.id.clone(),
+                },
+                event: events::ServerToClientEvents::UserJoined { user: user.clone() },
+            });
+        }
+
+        self.add_user(user);
+
+        Ok(())
+    }
+
+    fn leave(
+        &mut self,
+        user_id: &str,
+        effects: &mut Vec<Effect>,
+    ) -> Result<(), SkribblError> {
+        let user = self
+            .remove_user(user_id)
+            .ok_or_else(|| SkribblError::internal("User not found"))?;
+        let Self { room, users, .. } = self;
+        let room_id = &room.id.clone();
+
+        // Spectators only watch, so the game goes on as if they were never there.
+        // There is no one left to tell anything to once the last player is gone.
+        if user.is_spectator || room.amount_of_users == 0 {
+            return Ok(());
+        }
+
+        if room.state != state::RoomState::Waiting
+            && room.state != state::RoomState::Finished
+        {
+            if room.amount_of_users == 1 {
+                reset_room(room, user_id, effects);
+            } else {
+                handle_playing_room(users, room, room_id, user_id, effects)?;
+            }
+        }
+
+        if user_id == room.host_id {
+            handle_new_host(users, room, room_id, user_id, effects)?;
+        }
+
+        effects.push(Effect::Send {
+            to: events::WebSocketMessageType::Broadcast {
+                sender_id: user_id.to_string(),
+            },
+            event: events::ServerToClientEvents::UserLeft {
+                user_id: user_id.to_string(),
+            },
+        });
+
+        Ok(())
+    }
+}
+
+/// Returns the events that let `user` catch up with the current turn, i.e., the
+/// words to pick for the user drawing, or the word being drawn along with what
+/// has been drawn so far.
+fn current_turn_events(
+    room: &state::Room,
+    user: &state::User,
+) -> Vec<events::ServerToClientEvents> {
+    let state::RoomState::Playing {
+        playing_state,
+        current_user_id,
+        ..
+    } = &room.state
+    else {
+        return vec![];
+    };
+    let is_drawing = *current_user_id == user.id;
+
+    match playing_state {
+        state::PlayingState::PickingAWord { words_to_pick, .. } => {
+            if !is_drawing {
+                return vec![];
+            }
+
+            vec![events::ServerToClientEvents::PickAWord {
+                words_to_pick: words_to_pick.clone(),
+            }]
+        }
+        state::PlayingState::Drawing {
+            current_word,
+            revealed_indices,
+            ..
+        } => {
+            let mut catch_up_events = vec![events::ServerToClientEvents::NewWord {
+                word: if is_drawing || user.has_guessed {
+                    current_word.clone()
+                } else {
+                    utils::obfuscate_word_with_hints(current_word, revealed_indices)
+                },
+            }];
+
+            catch_up_events
+                .extend(events::ServerToClientEvents::canvas_snapshots(&room.canvas));
+
+            catch_up_events
+        }
+    }
+}
+
+fn on_pick_a_word(
+    word: String,
+    user_id: &str,
+    room: &mut state::Room,
+    effects: &mut Vec<Effect>,
+) -> Result<(), SkribblError> {
+    let draw_time_limit = room.draw_time_limit;
+    let state::RoomState::Playing {
+        playing_state: playing_state @ state::PlayingState::PickingAWord { .. },
+        current_user_id,
+        ..
+    } = &mut room.state
+    else {
+        return Err(SkribblError::rule_violation(
+            events::ErrorCode::InvalidRoomState,
+            "A word can only be picked at the start of a turn",
+        ));
+    };
+
+    if current_user_id != user_id {
+        return Err(SkribblError::rule_violation(
+            events::ErrorCode::NotYourTurn,
+            "Only the user drawing can pick a word",
+        ));
+    }
+
+    if let state::PlayingState::PickingAWord { words_to_pick, .. } = playing_state {
+        if !words_to_pick.contains(&word) {
+            return Err(SkribblError::rule_violation(
+                events::ErrorCode::InvalidWord,
+                "The word has to be one of the words to pick",
+            ));
+        }
+    }
+
+    effects.push(Effect::StopTimer);
+
+    *playing_state = state::PlayingState::Drawing {
+        current_word: word.clone(),
+        time_left: draw_time_limit,
+        revealed_indices: Vec::new(),
+    };
+    room.clear_canvas();
+
+    // TODO: Add check if we need to send this by checking if someone is
+    // drawing.
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Broadcast {
+            sender_id: user_id.to_string(),
+        },
+        event: events::ServerToClientEvents::NewWord {
+            word: utils::obfuscate_word(&word),
+        },
+    });
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::User {
+            receiver_id: user_id.to_string(),
+        },
+        event: events::ServerToClientEvents::NewWord { word },
+    });
+
+    effects.push(Effect::StartTimer);
+
+    Ok(())
+}
+
+/// Relays what the user drawing did on the canvas to everyone in the room, keeping
+/// it in the room's canvas history so users who join mid-turn can catch up.
+fn on_canvas_action(
+    action: state::CanvasAction,
+    user_id: &str,
+    room: &mut state::Room,
+    effects: &mut Vec<Effect>,
+) -> Result<(), SkribblError> {
+    let state::RoomState::Playing {
+        playing_state: state::PlayingState::Drawing { .. },
+        current_user_id,
+        ..
+    } = &room.state
+    else {
+        return Err(SkribblError::rule_violation(
+            events::ErrorCode::InvalidRoomState,
+            "Nothing can be drawn until a word is picked",
+        ));
+    };
+
+    if current_user_id != user_id {
+        return Err(SkribblError::rule_violation(
+            events::ErrorCode::NotYourTurn,
+            "Only the user drawing can draw",
+        ));
+    }
+
+    // Drawing on after this would leave users who join late with a different
+    // drawing than everyone else.
+    if !room.record_canvas_action(user_id, action.clone()) {
+        return Err(SkribblError::rule_violation(
+            events::ErrorCode::InvalidRoomState,
+            "The canvas is full",
+        ));
+    }
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Everyone,
+        event: events::ServerToClientEvents::from(action),
+    });
+
+    Ok(())
+}
+
+fn handle_new_host<'st>(
+    users: &'st [state::User],
+    room: &'st mut state::Room,
+    room_id: &str,
+    user_id_who_disconnected: &str,
+    effects: &mut Vec<Effect>,
+) -> Result<(), SkribblError> {
+    // We prefer users who are still connected.
+    let new_host = users
+        .iter()
+        .find(|user| user.is_playing_in(room_id) && user.is_connected)
+        .or_else(|| {
+            users
+                .iter()
+                .find(|user| user.is_playing_in(room_id))
+        })
+        .ok_or_else(|| {
+            SkribblError::internal("Cannot find any user to be the new host")
+        })?;
+
+    room.host_id = new_host.id.clone();
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Broadcast {
+            sender_id: user_id_who_disconnected.to_string(),
+        },
+        event: events::ServerToClientEvents::NewHost {
+            user_id: new_host.id.clone(),
+        },
+    });
+
+    Ok(())
+}
+
+fn handle_playing_room(
+    users: &mut [state::User],
+    room: &mut state::Room,
+    room_id: &str,
+    user_id_who_disconnected: &str,
+    effects: &mut Vec<Effect>,
+) -> Result<(), SkribblError> {
+    if let state::RoomState::Playing {
+        current_user_id, ..
+    } = &room.state
+    {
+        if current_user_id != user_id_who_disconnected {
+            return Ok(());
+        }
+    } else {
+        return Err(SkribblError::internal("Room is not being played"));
+    }
+
+    // We just stop the timer if the user who disconnected is the one who's
+    // drawing since we would be changing the timer no matter what.
+    effects.push(Effect::StopTimer);
+
+    let amount_of_users_who_has_not_drawn = users.iter().fold(0, |acc, user| {
+        if user.is_playing_in(room_id) && !user.has_drawn {
+            acc + 1
+        } else {
+            acc
+        }
+    });
+    let is_in_last_round = match &room.state {
+        state::RoomState::Playing { current_round, .. } => {
+            *current_round == room.max_rounds
+        }
+        _ => return Err(SkribblError::internal("Room is not being played")),
+    };
+
+    users.iter_mut().for_each(|user| {
+        if user.room_id == room_id {
+            user.has_guessed = false;
+        }
+    });
+
+    if amount_of_users_who_has_not_drawn == 0 && is_in_last_round {
+        room.state = state::RoomState::Finished;
+
+        effects.push(Effect::Send {
+            to: events::WebSocketMessageType::Broadcast {
+                sender_id: user_id_who_disconnected.to_string(),
+            },
+            event: events::ServerToClientEvents::EndGame,
+        });
+
+        return Ok(());
+    }
+
+    if amount_of_users_who_has_not_drawn == 0 && !is_in_last_round {
+        let state::RoomState::Playing { current_round, .. } = &mut room.state else {
+            return Err(SkribblError::internal("Room is not being played"));
+        };
+
+        *current_round += 1;
+
+        effects.push(Effect::Send {
+            to: events::WebSocketMessageType::Broadcast {
+                sender_id: user_id_who_disconnected.to_string(),
+            },
+            event: events::ServerToClientEvents::NewRound {
+                round: *current_round,
+            },
+        });
+
+        users.iter_mut().for_each(|user| {
+            if user.room_id == room_id {
+                user.has_drawn = false;
+            }
+        });
+
+        handle_new_turn(users, room, user_id_who_disconnected, room_id, effects)?;
+
+        effects.push(Effect::StartTimer);
+    }
+
+    if amount_of_users_who_has_not_drawn != 0 {
+        handle_new_turn(users, room, user_id_who_disconnected, room_id, effects)?;
+
+        effects.push(Effect::StartTimer);
+    }
+
+    Ok(())
+}
+
+fn handle_new_turn(
+    users: &mut [state::User],
+    room: &mut state::Room,
+    user_id_who_disconnected: &str,
+    room_id: &str,
+    effects: &mut Vec<Effect>,
+) -> Result<(), SkribblError> {
+    let state::RoomState::Playing {
+        playing_state,
+        current_user_id,
+        ..
+    } = &mut room.state
+    else {
+        return Err(SkribblError::internal("Room is not being played"));
+    };
+    let user_to_draw = utils::choose_user_in_a_room_randomly(users, room_id)?;
+
+    *current_user_id = user_to_draw.id.clone();
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Broadcast {
+            sender_id: user_id_who_disconnected.to_string(),
+        },
+        event: events::ServerToClientEvents::NewTurn {
+            user_id_to_draw: user_to_draw.id.clone(),
+        },
+    });
+
+    let words_to_pick = state::WordToDraw::get_words_to_pick(
+        &room.word_source,
+        room.amount_of_words_to_pick as usize,
+    );
+
+    *playing_state = state::PlayingState::PickingAWord {
+        words_to_pick: words_to_pick.clone(),
+        time_left: room.pick_word_time_limit,
+    };
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::User {
+            receiver_id: user_to_draw.id.clone(),
+        },
+        event: events::ServerToClientEvents::PickAWord { words_to_pick },
+    });
+
+    Ok(())
+}
+
+fn reset_room(room: &mut state::Room, user_id: &str, effects: &mut Vec<Effect>) {
+    room.state = state::RoomState::Waiting;
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Broadcast {
+            sender_id: user_id.to_string(),
+        },
+        event: events::ServerToClientEvents::ResetRoom,
+    });
+
+    effects.push(Effect::StopTimer);
+}
+
+fn on_update_settings(
+    settings: state::RoomSettings,
+    user_id: &str,
+    room: &mut state::Room,
+    effects: &mut Vec<Effect>,
+) -> Result<(), SkribblError> {
+    let error = if room.host_id != user_id {
+        Some((
+            events::ErrorCode::NotHost,
+            "Only the host can change the settings".to_string(),
+        ))
+    } else if room.state != state::RoomState::Waiting {
+        Some((
+            events::ErrorCode::InvalidRoomState,
+            "Settings can only be changed while waiting for players".to_string(),
+        ))
+    } else {
+        settings
+            .validate(room.amount_of_users)
+            .err()
+            .map(|message| (events::ErrorCode::InvalidSettings, message))
+    };
+
+    if let Some((code, message)) = error {
+        return Err(SkribblError::rule_violation(code, message));
+    }
+
+    room.apply_settings(&settings);
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Everyone,
+        event: events::ServerToClientEvents::SettingsUpdated { settings },
+    });
+
+    Ok(())
+}
+
+fn on_upload_custom_words(
+    words: &str,
+    mode: state::CustomWordsMode,
+    user_id: &str,
+    room: &mut state::Room,
+    effects: &mut Vec<Effect>,
+) -> Result<(), SkribblError> {
+    let custom_words = if room.host_id != user_id {
+        Err((
+            events::ErrorCode::NotHost,
+            "Only the host can upload custom words".to_string(),
+        ))
+    } else if room.visibility != state::Visibility::Private {
+        Err((
+            events::ErrorCode::RoomNotPrivate,
+            "Custom words can only be used in private rooms".to_string(),
+        ))
+    } else if room.state != state::RoomState::Waiting {
+        Err((
+            events::ErrorCode::InvalidRoomState,
+            "Custom words can only be uploaded while waiting for players".to_string(),
+        ))
+    } else {
+        state::WordSource::parse_custom_words(words, mode)
+            .map_err(|message| (events::ErrorCode::InvalidCustomWords, message))
+    };
+
+    let custom_words = match custom_words {
+        Ok(custom_words) => custom_words,
+        Err((code, message)) => {
+            return Err(SkribblError::rule_violation(code, message));
+        }
+    };
+
+    // We only tell everyone how many words there are since the words themselves
+    // are what they will be guessing.
+    let message = match mode {
+        state::CustomWordsMode::Mix => format!(
+            "The host added {} custom words to the default words",
+            custom_words.len()
+        ),
+        state::CustomWordsMode::Replace => format!(
+            "The host replaced the default words with {} custom words",
+            custom_words.len()
+        ),
+    };
+
+    room.word_source.custom_words = custom_words;
+    room.word_source.custom_words_mode = mode;
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Everyone,
+        event: events::ServerToClientEvents::SystemMessage { message },
+    });
+
+    Ok(())
+}
+
+fn on_select_word_pack(
+    category: &str,
+    language: &str,
+    user_id: &str,
+    room: &mut state::Room,
+    effects: &mut Vec<Effect>,
+    word_packs: &word_packs::WordPacks,
+) -> Result<(), SkribblError> {
+    let pack = if room.host_id != user_id {
+        Err((
+            events::ErrorCode::NotHost,
+            "Only the host can select a word pack".to_string(),
+        ))
+    } else if room.state != state::RoomState::Waiting {
+        Err((
+            events::ErrorCode::InvalidRoomState,
+            "A word pack can only be selected while waiting for players".to_string(),
+        ))
+    } else {
+        word_packs.get(category, language).ok_or_else(|| {
+            (
+                events::ErrorCode::WordPackNotFound,
+                "Word pack not found".to_string(),
+            )
+        })
+    };
+
+    let pack = match pack {
+        Ok(pack) => pack,
+        Err((code, message)) => {
+            return Err(SkribblError::rule_violation(code, message));
+        }
+    };
+
+    let message = format!(
+        "The host selected the \"{}\" word pack ({})",
+        pack.category, pack.language
+    );
+
+    room.word_source.pack = Some(pack);
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Everyone,
+        event: events::ServerToClientEvents::SystemMessage { message },
+    });
+
+    Ok(())
+}
+
+fn start_game_event(
+    room_id: &str,
+    user_id: &str,
+    room: &mut state::Room,
+    users: &mut [state::User],
+    effects: &mut Vec<Effect>,
+) -> Result<(), SkribblError> {
+    if room.amount_of_users == 1 {
+        return Err(SkribblError::rule_violation(
+            events::ErrorCode::NotEnoughPlayers,
+            "Need at least 2 players to start the game",
+        ));
+    }
+
+    if room.host_id != user_id {
+        return Err(SkribblError::rule_violation(
+            events::ErrorCode::NotHost,
+            "Only the host can start the game",
+        ));
+    }
+
+    if room.state != state::RoomState::Waiting {
+        return Err(SkribblError::rule_violation(
+            events::ErrorCode::GameAlreadyStarted,
+            "Game has already started",
+        ));
+    }
+
+    let user_to_draw = utils::choose_user_in_a_room_randomly(users, room_id)?;
+
+    let words_to_pick = state::WordToDraw::get_words_to_pick(
+        &room.word_source,
+        room.amount_of_words_to_pick as usize,
+    );
+
+    room.state = state::RoomState::Playing {
+        playing_state: state::PlayingState::PickingAWord {
+            words_to_pick: words_to_pick.clone(),
+            time_left: room.pick_word_time_limit,
+        },
+        current_user_id: user_to_draw.id.clone(),
+        current_round: 1,
+    };
+
+    user_to_draw.has_drawn = true;
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Everyone,
+        event: events::ServerToClientEvents::StartGame,
+    });
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Everyone,
+        event: events::ServerToClientEvents::NewTurn {
+            user_id_to_draw: user_to_draw.id.clone(),
+        },
+    });
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::User {
+            receiver_id: user_to_draw.id.clone(),
+        },
+        event: events::ServerToClientEvents::PickAWord { words_to_pick },
+    });
+
+    effects.push(Effect::StartTimer);
+
+    Ok(())
+}
+
+fn on_tick(
+    room_id: &str,
+    room: &mut state::Room,
+    users: &mut [state::User],
+    effects: &mut Vec<Effect>,
+) -> Result<(), SkribblError> {
+    let draw_time_limit = room.draw_time_limit;
+    let state::RoomState::Playing {
+        playing_state,
+        current_user_id,
+        ..
+    } = &mut room.state
+    else {
+        effects.push(Effect::StopTimer);
+
+        return Ok(());
+    };
+
+    if let state::PlayingState::Drawing {
+        current_word,
+        time_left,
+        revealed_indices,
+    } = playing_state
+    {
+        if utils::is_time_to_reveal_hint(*time_left, draw_time_limit) {
+            if let Some(index) =
+                utils::pick_letter_to_reveal(current_word, revealed_indices)
+            {
+                revealed_indices.push(index);
+
+                send_hint(
+                    room_id,
+                    current_user_id,
+                    &utils::obfuscate_word_with_hints(current_word, revealed_indices),
+                    users,
+                    effects,
+                )?;
+            }
+        }
+    }
+
+    let time_left = match playing_state {
+        state::PlayingState::Drawing { time_left, .. } => time_left,
+        state::PlayingState::PickingAWord { time_left, .. } => time_left,
+    };
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Everyone,
+        event: events::ServerToClientEvents::Tick {
+            time_left: *time_left,
+        },
+    });
+
+    if *time_left == 0 {
+        effects.push(Effect::StopTimer);
+
+        return on_timer_reached_zero(room_id, room, users, effects);
+    }
+
+    *time_left -= 1;
+
+    Ok(())
+}
+
+/// Sends the partly revealed word to everyone in the room except the user drawing
+/// and the users who already guessed the word.
+fn send_hint(
+    room_id: &str,
+    user_id_drawing: &str,
+    hint: &str,
+    users: &[state::User],
+    effects: &mut Vec<Effect>,
+) -> Result<(), SkribblError> {
+    for user in users.iter().filter(|user| {
+        user.room_id == room_id && user.id != user_id_drawing && !user.has_guessed
+    }) {
+        effects.push(Effect::Send {
+            to: events::WebSocketMessageType::User {
+                receiver_id: user.id.clone(),
+            },
+            event: events::ServerToClientEvents::Hint {
+                word: hint.to_string(),
+            },
+        });
+    }
+
+    Ok(())
+}
+
+/// Moves on once the time to pick a word or to draw is up, starting the timer
+/// again unless the game has ended.
+fn on_timer_reached_zero(
+    room_id: &str,
+    room: &mut state::Room,
+    users: &mut [state::User],
+    effects: &mut Vec<Effect>,
+) -> Result<(), SkribblError> {
+    let state::RoomState::Playing {
+        playing_state,
+        current_round,
+        ..
+    } = &mut room.state
+    else {
+        return Err(SkribblError::internal("Room is not being played"));
+    };
+
+    match *playing_state {
+        state::PlayingState::PickingAWord { .. } => start_drawing(room, effects)?,
+        state::PlayingState::Drawing { .. } => {
+            if users
+                .iter()
+                .any(|user| user.is_playing_in(room_id) && !user.has_drawn)
+            {
+                next_turn(room_id, effects, users, room)?;
+            } else if *current_round == room.max_rounds {
+                return end_game(room_id, effects, room, users);
+            } else {
+                next_round(room_id, effects, users, room)?;
+            }
+        }
+    }
+
+    effects.push(Effect::StartTimer);
+
+    Ok(())
+}
+
+fn on_message(
+    message: String,
+    room_id: &str,
+    user_id: &str,
+    room: &mut state::Room,
+    users: &mut [state::User],
+    effects: &mut Vec<Effect>,
+) -> Result<(), SkribblError> {
+    let draw_time_limit = room.draw_time_limit;
+
+    if let state::RoomState::Playing {
+        playing_state:
+            state::PlayingState::Drawing {
+                current_word,
+                time_left,
+                ..
+            },
+        current_round,
+        current_user_id,
+    } = &mut room.state
+    {
+        let normalized_word = similarity::normalize(current_word);
+        let normalized_message = similarity::normalize(&message);
+        let is_exact_guess = normalized_word == normalized_message;
+        let is_close_guess = !is_exact_guess
+            && similarity::is_close_guess(&normalized_word, &normalized_message);
+
+        if is_exact_guess || is_close_guess {
+            // We do this (getting the user twice, on here and on `user_guessed`)
+            // because `user_guessed` needs to mutably borrow the `users` for the
+            // scoring system.
+            let user = users
+                .iter()
+                .find(|user| user.id == user_id)
+                .ok_or_else(|| SkribblError::internal("User not found"))?;
+
+            if user.is_spectator {
+                return Err(SkribblError::rule_violation(
+                    events::ErrorCode::NotAllowedForSpectators,
+                    "Spectators cannot guess the word",
+                ));
+            }
+
+            // Anyone who already knows the word could give it away, even with a
+            // message that is only close to it.
+            if user_id == current_user_id || user.has_guessed {
+                return Err(SkribblError::rule_violation(
+                    events::ErrorCode::WordExposed,
+                    "You cannot expose the word being drawn",
+                ));
+            }
+        }
+
+        if is_exact_guess {
+            user_guessed(
+                room_id,
+                user_id,
+                current_user_id,
+                current_word,
+                *time_left,
+                draw_time_limit,
+                effects,
+                users,
+            )?;
+
+            if !users.iter().any(|user| {
+                if *current_user_id == user.id {
+                    return false;
+                }
+
+                user.is_playing_in(room_id) && !user.has_guessed
+            }) {
+                let has_everyone_drawn = !users
+                    .iter()
+                    .any(|user| user.is_playing_in(room_id) && !user.has_drawn);
+
+                if has_everyone_drawn && *current_round == room.max_rounds {
+                    return end_game(room_id, effects, room, users);
+                }
+
+                effects.push(Effect::StopTimer);
+
+                if !has_everyone_drawn {
+                    let res = next_turn(room_id, effects, users, room);
+
+                    effects.push(Effect::StartTimer);
+
+                    return res;
+                }
+
+                let res = next_round(room_id, effects, users, room);
+
+                effects.push(Effect::StartTimer);
+
+                return res;
+            }
+
+            return Ok(());
+        }
+
+        if is_close_guess {
+            // We only tell the user who guessed since broadcasting a guess this
+            // close would expose the word to everyone else.
+            effects.push(Effect::Send {
+                to: events::WebSocketMessageType::User {
+                    receiver_id: user_id.to_string(),
+                },
+                event: events::ServerToClientEvents::CloseGuess { guess: message },
+            });
+
+            return Ok(());
+        }
+    }
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Everyone,
+        event: events::ServerToClientEvents::Message {
+            user_id: user_id.to_string(),
+            message,
+        },
+    });
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn user_guessed(
+    room_id: &str,
+    user_id: &str,
+    user_id_drawing: &str,
+    word_to_draw: &str,
+    time_left: u8,
+    time_limit: u8,
+    effects: &mut Vec<Effect>,
+    users: &mut [state::User],
+) -> Result<(), SkribblError> {
+    let guess_order = users
+        .iter()
+        .filter(|user| user.room_id == room_id && user.has_guessed)
+        .count();
+    let guesser_score = scoring::guesser_score(time_left, time_limit, guess_order);
+    let drawer_score = scoring::drawer_score(time_left, time_limit);
+
+    if let Some(user_drawing) = users
+        .iter_mut()
+        .find(|user| user.id == user_id_drawing)
+    {
+        user_drawing.score = user_drawing.score.saturating_add(drawer_score);
+
+        effects.push(Effect::Send {
+            to: events::WebSocketMessageType::Everyone,
+            event: events::ServerToClientEvents::AddScore {
+                user_id: user_id_drawing.to_string(),
+                score: drawer_score,
+            },
+        });
+    }
+
+    let user = users
+        .iter_mut()
+        .find(|user| user.id == user_id)
+        .ok_or_else(|| SkribblError::internal("User not found"))?;
+
+    user.has_guessed = true;
+    user.score = user.score.saturating_add(guesser_score);
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Everyone,
+        event: events::ServerToClientEvents::AddScore {
+            user_id: user_id.to_string(),
+            score: guesser_score,
+        },
+    });
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Everyone,
+        event: events::ServerToClientEvents::UserGuessed {
+            user_id: user_id.to_string(),
+        },
+    });
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Everyone,
+        event: events::ServerToClientEvents::SystemMessage {
+            message: format!("{} has guessed the word!", user.display_name.clone()),
+        },
+    });
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::User {
+            receiver_id: user_id.to_string(),
+        },
+        event: events::ServerToClientEvents::RevealWord {
+            word: word_to_draw.to_string(),
+        },
+    });
+
+    Ok(())
+}
+
+fn start_drawing(
+    room: &mut state::Room,
+    effects: &mut Vec<Effect>,
+) -> Result<(), SkribblError> {
+    let draw_time_limit = room.draw_time_limit;
+
+    room.clear_canvas();
+
+    let state::RoomState::Playing {
+        playing_state,
+        current_user_id,
+        ..
+    } = &mut room.state
+    else {
+        return Err(SkribblError::internal("Room is not being played"));
+    };
+
+    let word_to_draw = match playing_state {
+        state::PlayingState::Drawing { .. } => {
+            return Err(SkribblError::internal("A word is already being drawn"));
+        }
+        state::PlayingState::PickingAWord { words_to_pick, .. }
+            if words_to_pick.is_empty() =>
+        {
+            return Err(SkribblError::internal("There are no words to pick from"));
+        }
+        state::PlayingState::PickingAWord { words_to_pick, .. } => words_to_pick
+            [rand::thread_rng().gen_range(0..words_to_pick.len())]
+        .to_string(),
+    };
+
+    *playing_state = state::PlayingState::Drawing {
+        current_word: word_to_draw.clone(),
+        time_left: draw_time_limit,
+        revealed_indices: Vec::new(),
+    };
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::User {
+            receiver_id: current_user_id.clone(),
+        },
+        event: events::ServerToClientEvents::NewWord {
+            word: word_to_draw.clone(),
+        },
+    });
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Broadcast {
+            sender_id: current_user_id.clone(),
+        },
+        event: events::ServerToClientEvents::NewWord {
+            word: utils::obfuscate_word(&word_to_draw),
+        },
+    });
+
+    Ok(())
+}
+
+fn next_round(
+    room_id: &str,
+    effects: &mut Vec<Effect>,
+    users: &mut [state::User],
+    room: &mut state::Room,
+) -> Result<(), SkribblError> {
+    let state::RoomState::Playing { current_round, .. } = &mut room.state else {
+        return Err(SkribblError::internal("Room is not being played"));
+    };
+
+    if users
+        .iter()
+        .any(|user| user.is_playing_in(room_id) && !user.has_drawn)
+    {
+        return Err(SkribblError::internal(
+            "A new round cannot start before everyone has drawn",
+        ));
+    }
+
+    if *current_round == room.max_rounds {
+        return Err(SkribblError::internal(
+            "A new round cannot start after the last one",
+        ));
+    }
+
+    users.iter_mut().for_each(|user| {
+        if user.room_id == room_id {
+            user.has_drawn = false;
+        }
+    });
+
+    *current_round += 1;
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Everyone,
+        event: events::ServerToClientEvents::NewRound {
+            round: *current_round,
+        },
+    });
+
+    next_turn(room_id, effects, users, room)
+}
+
+fn next_turn(
+    room_id: &str,
+    effects: &mut Vec<Effect>,
+    users: &mut [state::User],
+    room: &mut state::Room,
+) -> Result<(), SkribblError> {
+    let state::RoomState::Playing {
+        playing_state,
+        current_user_id,
+        ..
+    } = &mut room.state
+    else {
+        return Err(SkribblError::internal("Room is not being played"));
+    };
+
+    users.iter_mut().for_each(|user| {
+        if user.room_id == room_id {
+            user.has_guessed = false;
+        }
+    });
+
+    let mut users_in_room_who_has_not_drawn = users
+        .iter_mut()
+        .filter(|user| user.is_playing_in(room_id) && !user.has_drawn)
+        .collect::<Vec<&mut state::User>>();
+
+    let users_in_room_who_has_not_drawn_length = users_in_room_who_has_not_drawn.len();
+    let user_to_draw = &mut *users_in_room_who_has_not_drawn
+        [rand::thread_rng().gen_range(0..users_in_room_who_has_not_drawn_length)];
+    let words_to_pick = state::WordToDraw::get_words_to_pick(
+        &room.word_source,
+        room.amount_of_words_to_pick as usize,
+    );
+
+    *playing_state = state::PlayingState::PickingAWord {
+        words_to_pick: words_to_pick.clone(),
+        time_left: room.pick_word_time_limit,
+    };
+    user_to_draw.has_drawn = true;
+    *current_user_id = user_to_draw.id.clone();
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Everyone,
+        event: events::ServerToClientEvents::NewTurn {
+            user_id_to_draw: user_to_draw.id.clone(),
+        },
+    });
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::User {
+            receiver_id: user_to_draw.id.clone(),
+        },
+        event: events::ServerToClientEvents::PickAWord { words_to_pick },
+    });
+
+    Ok(())
+}
+
+fn end_game(
+    room_id: &str,
+    effects: &mut Vec<Effect>,
+    room: &mut state::Room,
+    users: &mut [state::User],
+) -> Result<(), SkribblError> {
+    effects.push(Effect::StopTimer);
+
+    room.state = state::RoomState::Finished;
+
+    users
+        .iter_mut()
+        .filter(|user| user.room_id == room_id)
+        .for_each(|user| {
+            user.has_drawn = false;
+            user.has_guessed = false;
+            user.score = 0;
+        });
+
+    effects.push(Effect::Send {
+        to: events::WebSocketMessageType::Everyone,
+        event: events::ServerToClientEvents::EndGame,
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: &str) -> state::User {
+        state::UserBuilder::default()
+            .id(id.to_string())
+            .display_name(id.to_string())
+            .room_id("a".to_string())
+            .build()
+            .unwrap()
+    }
+
+    /// A room hosted by the first of `user_ids`, with the rest of them joined.
+    fn test_room(user_ids: &[&str]) -> Room {
+        let room = state::RoomBuilder::default()
+            .id("a".to_string())
+            .host_id(user_ids[0].to_string())
+            .build()
+            .unwrap();
+        let mut game_room =
+            Room::new(room, user(user_ids[0]), word_packs::WordPacks::default());
+
+        for user_id in &user_ids[1..] {
+            game_room
+                .apply(Command::Join {
+                    user: user(user_id),
+                    only_if_waiting: false,
+                })
+                .unwrap();
+        }
+
+        game_room
+    }
+
+    fn send_event(
+        game_room: &mut Room,
+        user_id: &str,
+        event: events::ClientToServerEvents,
+    ) -> Result<Vec<Effect>, SkribblError> {
+        game_room.apply(Command::Event {
+            user_id: user_id.to_string(),
+            event,
+        })
+    }
+
+    fn rule_violation(result: Result<Vec<Effect>, SkribblError>) -> events::ErrorCode {
+        match result {
+            Err(SkribblError::RuleViolation { code, .. }) => code,
+            result => panic!("Expected a rule violation, got {:?}", result),
+        }
+    }
+
+    /// The events among `effects`, leaving out who they are sent to.
+    fn sent(effects: &[Effect]) -> Vec<events::ServerToClientEvents> {
+        effects
+            .iter()
+            .filter_map(|effect| match effect {
+                Effect::Send { event, .. } => Some(event.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn current_user_id(game_room: &Room) -> String {
+        match &game_room.room.state {
+            state::RoomState::Playing {
+                current_user_id, ..
+            } => current_user_id.clone(),
+            state => panic!("Expected the room to be playing, got {:?}", state),
+        }
+    }
+
+    fn set_time_left(game_room: &mut Room, new_time_left: u8) {
+        if let state::RoomState::Playing {
+            playing_state:
+                state::PlayingState::PickingAWord { time_left, .. }
+                | state::PlayingState::Drawing { time_left, .. },
+            ..
+        } = &mut game_room.room.state
+        {
+            *time_left = new_time_left;
+        }
+    }
+
+    /// Has `user_id` pick `word`, which is made one of the words to pick first.
+    fn pick_word(
+        game_room: &mut Room,
+        user_id: &str,
+        word: &str,
+    ) -> Result<Vec<Effect>, SkribblError> {
+        if let state::RoomState::Playing {
+            playing_state: state::PlayingState::PickingAWord { words_to_pick, .. },
+            ..
+        } = &mut game_room.room.state
+        {
+            words_to_pick[0] = word.to_string();
+        }
+
+        send_event(
+            game_room,
+            user_id,
+            events::ClientToServerEvents::PickAWord {
+                word: word.to_string(),
+            },
+        )
+    }
+
+    /// Starts the game and picks the word "apple" for the first turn. Returns the
+    /// id of the user drawing.
+    fn start_drawing_apple(game_room: &mut Room) -> String {
+        let host_id = game_room.room.host_id.clone();
+
+        send_event(game_room, &host_id, events::ClientToServerEvents::StartGame).unwrap();
+
+        let user_id_drawing = current_user_id(game_room);
+
+        pick_word(game_room, &user_id_drawing, "apple").unwrap();
+
+        user_id_drawing
+    }
+
+    #[test]
+    fn test_rooms_keep_their_users() {
+        let mut game_room = test_room(&["host"]);
+        let mut spectator = user("spectator");
+
+        spectator.is_spectator = true;
+        game_room.add_user(spectator);
+        game_room.add_user(user("player"));
+
+        assert_eq!(game_room.room.amount_of_users, 2);
+        assert_eq!(game_room.users.len(), 3);
+        assert_eq!(
+            game_room
+                .players()
+                .iter()
+                .map(|user| user.id.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["host", "player"]
+        );
+        assert!(game_room.user("nobody").is_none());
+
+        assert_eq!(
+            game_room
+                .remove_user("player")
+                .map(|user| user.id),
+            Some("player".to_string())
+        );
+        assert_eq!(game_room.remove_user("player"), None);
+        assert!(game_room.remove_user("spectator").is_some());
+        assert_eq!(game_room.room.amount_of_users, 1);
+    }
+
+    #[test]
+    fn test_is_available() {
+        let mut game_room = test_room(&["host"]);
+
+        assert!(game_room.is_available());
+
+        game_room.room.max_users = 1;
+
+        assert!(!game_room.is_available());
+
+        game_room.room.max_users = 8;
+        game_room.room.state = state::RoomState::Finished;
+
+        assert!(!game_room.is_available());
+    }
+
+    #[test]
+    fn test_join() {
+        let mut game_room = test_room(&["host"]);
+        let player = user("player");
+
+        assert_eq!(
+            game_room
+                .apply(Command::Join {
+                    user: player.clone(),
+                    only_if_waiting: true,
+                })
+                .unwrap(),
+            vec![Effect::Send {
+                to: events::WebSocketMessageType::Broadcast {
+                    sender_id: "player".to_string(),
+                },
+                event: events::ServerToClientEvents::UserJoined { user: player },
+            }]
+        );
+
+        game_room.room.max_users = 2;
+
+        assert_eq!(
+            rule_violation(game_room.apply(Command::Join {
+                user: user("late"),
+                only_if_waiting: false,
+            })),
+            events::ErrorCode::RoomFull
+        );
+
+        let mut spectator = user("spectator");
+
+        spectator.is_spectator = true;
+
+        assert_eq!(
+            game_room.apply(Command::Join {
+                user: spectator,
+                only_if_waiting: false,
+            }),
+            Ok(vec![])
+        );
+        assert_eq!(game_room.users.len(), 3);
+    }
+
+    #[test]
+    fn test_join_a_game_being_played() {
+        let mut game_room = test_room(&["host", "player"]);
+
+        send_event(
+            &mut game_room,
+            "host",
+            events::ClientToServerEvents::StartGame,
+        )
+        .unwrap();
+
+        assert_eq!(
+            rule_violation(game_room.apply(Command::Join {
+                user: user("matched"),
+                only_if_waiting: true,
+            })),
+            events::ErrorCode::RoomNotAvailable
+        );
+        assert!(game_room
+            .apply(Command::Join {
+                user: user("invited"),
+                only_if_waiting: false,
+            })
+            .is_ok());
+
+        game_room.room.state = state::RoomState::Finished;
+
+        assert_eq!(
+            rule_violation(game_room.apply(Command::Join {
+                user: user("late"),
+                only_if_waiting: false,
+            })),
+            events::ErrorCode::RoomNotAvailable
+        );
+    }
+
+    #[test]
+    fn test_start_game() {
+        let mut game_room = test_room(&["host"]);
+
+        assert_eq!(
+            rule_violation(send_event(
+                &mut game_room,
+                "host",
+                events::ClientToServerEvents::StartGame
+            )),
+            events::ErrorCode::NotEnoughPlayers
+        );
+
+        let mut game_room = test_room(&["host", "player"]);
+
+        assert_eq!(
+            rule_violation(send_event(
+                &mut game_room,
+                "player",
+                events::ClientToServerEvents::StartGame
+            )),
+            events::ErrorCode::NotHost
+        );
+
+        let effects = send_event(
+            &mut game_room,
+            "host",
+            events::ClientToServerEvents::StartGame,
+        )
+        .unwrap();
+        let user_id_drawing = current_user_id(&game_room);
+        let state::RoomState::Playing {
+            playing_state:
+                state::PlayingState::PickingAWord {
+                    words_to_pick,
+                    time_left,
+                },
+            current_round,
+            ..
+        } = game_room.room.state.clone()
+        else {
+            panic!("Expected a word to be picked");
+        };
+
+        assert_eq!(current_round, 1);
+        assert_eq!(time_left, game_room.room.pick_word_time_limit);
+        assert!(
+            game_room
+                .user(&user_id_drawing)
+                .unwrap()
+                .has_drawn
+        );
+        assert_eq!(
+            effects,
+            vec![
+                Effect::Send {
+                    to: events::WebSocketMessageType::Everyone,
+                    event: events::ServerToClientEvents::StartGame,
+                },
+                Effect::Send {
+                    to: events::WebSocketMessageType::Everyone,
+                    event: events::ServerToClientEvents::NewTurn {
+                        user_id_to_draw: user_id_drawing.clone(),
+                    },
+                },
+                Effect::Send {
+                    to: events::WebSocketMessageType::User {
+                        receiver_id: user_id_drawing,
+                    },
+                    event: events::ServerToClientEvents::PickAWord { words_to_pick },
+                },
+                Effect::StartTimer,
+            ]
+        );
+        assert_eq!(
+            rule_violation(send_event(
+                &mut game_room,
+                "host",
+                events::ClientToServerEvents::StartGame
+            )),
+            events::ErrorCode::GameAlreadyStarted
+        );
+    }
+
+    #[test]
+    fn test_pick_a_word() {
+        let mut game_room = test_room(&["host", "player"]);
+
+        assert_eq!(
+            rule_violation(send_event(
+                &mut game_room,
+                "host",
+                events::ClientToServerEvents::PickAWord {
+                    word: "apple".to_string(),
+                }
+            )),
+            events::ErrorCode::InvalidRoomState
+        );
+
+        send_event(
+            &mut game_room,
+            "host",
+            events::ClientToServerEvents::StartGame,
+        )
+        .unwrap();
+
+        let user_id_drawing = current_user_id(&game_room);
+        let user_id_guessing = if user_id_drawing == "host" {
+            "player"
+        } else {
+            "host"
+        };
+
+        assert_eq!(
+            rule_violation(pick_word(&mut game_room, user_id_guessing, "apple")),
+            events::ErrorCode::NotYourTurn
+        );
+        assert_eq!(
+            rule_violation(send_event(
+                &mut game_room,
+                &user_id_drawing,
+                events::ClientToServerEvents::PickAWord {
+                    word: "not one of the words to pick".to_string(),
+                }
+            )),
+            events::ErrorCode::InvalidWord
+        );
+
+        let effects = pick_word(&mut game_room, &user_id_drawing, "apple").unwrap();
+
+        assert_eq!(
+            effects,
+            vec![
+                Effect::StopTimer,
+                Effect::Send {
+                    to: events::WebSocketMessageType::Broadcast {
+                        sender_id: user_id_drawing.clone(),
+                    },
+                    event: events::ServerToClientEvents::NewWord {
+                        word: utils::obfuscate_word("apple"),
+                    },
+                },
+                Effect::Send {
+                    to: events::WebSocketMessageType::User {
+                        receiver_id: user_id_drawing,
+                    },
+                    event: events::ServerToClientEvents::NewWord {
+                        word: "apple".to_string(),
+                    },
+                },
+                Effect::StartTimer,
+            ]
+        );
+        assert_eq!(
+            game_room.room.state,
+            state::RoomState::Playing {
+                playing_state: state::PlayingState::Drawing {
+                    current_word: "apple".to_string(),
+                    time_left: game_room.room.draw_time_limit,
+                    revealed_indices: vec![],
+                },
+                current_user_id: current_user_id(&game_room),
+                current_round: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_canvas_actions_are_relayed_and_kept() {
+        let mut game_room = test_room(&["host", "player"]);
+        let user_id_drawing = start_drawing_apple(&mut game_room);
+
+        assert_eq!(
+            send_event(
+                &mut game_room,
+                &user_id_drawing,
+                events::ClientToServerEvents::PointerDown
+            ),
+            Ok(vec![Effect::Send {
+                to: events::WebSocketMessageType::Everyone,
+                event: events::ServerToClientEvents::PointerDown,
+            }])
+        );
+        assert_eq!(
+            game_room.room.canvas,
+            vec![state::CanvasAction::PointerDown]
+        );
+
+        let user_id_guessing = if user_id_drawing == "host" {
+            "player"
+        } else {
+            "host"
+        };
+
+        assert_eq!(
+            rule_violation(send_event(
+                &mut game_room,
+                user_id_guessing,
+                events::ClientToServerEvents::PointerDown
+            )),
+            events::ErrorCode::NotYourTurn
+        );
+
+        game_room.room.canvas_points = utils::consts::MAX_CANVAS_POINTS;
+
+        assert_eq!(
+            rule_violation(send_event(
+                &mut game_room,
+                &user_id_drawing,
+                events::ClientToServerEvents::PointerUp
+            )),
+            events::ErrorCode::InvalidRoomState
+        );
+        assert_eq!(
+            game_room.room.canvas,
+            vec![state::CanvasAction::PointerDown]
+        );
+    }
+
+    #[test]
+    fn test_tick_counts_down() {
+        let mut game_room = test_room(&["host", "player"]);
+
+        assert_eq!(game_room.apply(Command::Tick), Ok(vec![Effect::StopTimer]));
+
+        start_drawing_apple(&mut game_room);
+
+        let draw_time_limit = game_room.room.draw_time_limit;
+
+        assert_eq!(
+            game_room.apply(Command::Tick),
+            Ok(vec![Effect::Send {
+                to: events::WebSocketMessageType::Everyone,
+                event: events::ServerToClientEvents::Tick {
+                    time_left: draw_time_limit,
+                },
+            }])
+        );
+        assert!(matches!(
+            game_room.room.state,
+            state::RoomState::Playing {
+                playing_state: state::PlayingState::Drawing { time_left, .. },
+                ..
+            } if time_left == draw_time_limit - 1
+        ));
+    }
+
+    #[test]
+    fn test_tick_reveals_hints() {
+        let mut game_room = test_room(&["host", "player"]);
+        let user_id_drawing = start_drawing_apple(&mut game_room);
+        let user_id_guessing = game_room
+            .users
+            .iter()
+            .find(|user| user.id != user_id_drawing)
+            .unwrap()
+            .id
+            .clone();
+        let time_left = (game_room.room.draw_time_limit as u16
+            * utils::consts::HINT_PERCENTAGES_OF_TIME_LEFT[0] as u16
+            / 100) as u8;
+
+        set_time_left(&mut game_room, time_left);
+
+        let effects = game_room.apply(Command::Tick).unwrap();
+
+        assert_eq!(effects.len(), 2);
+        assert!(matches!(
+            &effects[0],
+            Effect::Send {
+                to: events::WebSocketMessageType::User { receiver_id },
+                event: events::ServerToClientEvents::Hint { word },
+            } if *receiver_id == user_id_guessing && word.chars().filter(|c| *c != '*').count() == 1
+        ));
+    }
+
+    #[test]
+    fn test_word_is_picked_when_time_is_up() {
+        let mut game_room = test_room(&["host", "player"]);
+
+        send_event(
+            &mut game_room,
+            "host",
+            events::ClientToServerEvents::StartGame,
+        )
+        .unwrap();
+        set_time_left(&mut game_room, 0);
+
+        let effects = game_room.apply(Command::Tick).unwrap();
+        let state::RoomState::Playing {
+            playing_state: state::PlayingState::Drawing { current_word, .. },
+            ..
+        } = &game_room.room.state
+        else {
+            panic!("Expected the room to be drawing");
+        };
+
+        assert_eq!(
+            sent(&effects),
+            vec![
+                events::ServerToClientEvents::Tick { time_left: 0 },
+                events::ServerToClientEvents::NewWord {
+                    word: current_word.clone(),
+                },
+                events::ServerToClientEvents::NewWord {
+                    word: utils::obfuscate_word(current_word),
+                },
+            ]
+        );
+        assert_eq!(effects[1], Effect::StopTimer);
+        assert_eq!(effects.last(), Some(&Effect::StartTimer));
+    }
+
+    #[test]
+    fn test_messages() {
+        let mut game_room = test_room(&["host", "player", "other"]);
+        let user_id_drawing = start_drawing_apple(&mut game_room);
+        let user_id_guessing = game_room
+            .users
+            .iter()
+            .find(|user| user.id != user_id_drawing)
+            .unwrap()
+            .id
+            .clone();
+
+        assert_eq!(
+            send_event(
+                &mut game_room,
+                &user_id_guessing,
+                events::ClientToServerEvents::Message {
+                    message: "hello".to_string(),
+                }
+            ),
+            Ok(vec![Effect::Send {
+                to: events::WebSocketMessageType::Everyone,
+                event: events::ServerToClientEvents::Message {
+                    user_id: user_id_guessing.clone(),
+                    message: "hello".to_string(),
+                },
+            }])
+        );
+        assert_eq!(
+            send_event(
+                &mut game_room,
+                &user_id_guessing,
+                events::ClientToServerEvents::Message {
+                    message: "appl".to_string(),
+                }
+            ),
+            Ok(vec![Effect::Send {
+                to: events::WebSocketMessageType::User {
+                    receiver_id: user_id_guessing,
+                },
+                event: events::ServerToClientEvents::CloseGuess {
+                    guess: "appl".to_string(),
+                },
+            }])
+        );
+        assert_eq!(
+            rule_violation(send_event(
+                &mut game_room,
+                &user_id_drawing,
+                events::ClientToServerEvents::Message {
+                    message: "apple".to_string(),
+                }
+            )),
+            events::ErrorCode::WordExposed
+        );
+    }
+
+    #[test]
+    fn test_close_guesses_ignore_case_and_accents() {
+        let mut game_room = test_room(&["host", "player", "other"]);
+        let user_id_drawing = start_drawing_apple(&mut game_room);
+        let user_id_guessing = game_room
+            .users
+            .iter()
+            .find(|user| user.id != user_id_drawing)
+            .unwrap()
+            .id
+            .clone();
+
+        for guess in ["APLE", "ÂPPL"] {
+            assert_eq!(
+                send_event(
+                    &mut game_room,
+                    &user_id_guessing,
+                    events::ClientToServerEvents::Message {
+                        message: guess.to_string(),
+                    }
+                ),
+                Ok(vec![Effect::Send {
+                    to: events::WebSocketMessageType::User {
+                        receiver_id: user_id_guessing.clone(),
+                    },
+                    event: events::ServerToClientEvents::CloseGuess {
+                        guess: guess.to_string(),
+                    },
+                }])
+            );
+        }
+    }
+
+    #[test]
+    fn test_drawer_cannot_send_a_close_guess() {
+        let mut game_room = test_room(&["host", "player"]);
+        let user_id_drawing = start_drawing_apple(&mut game_room);
+
+        assert_eq!(
+            rule_violation(send_event(
+                &mut game_room,
+                &user_id_drawing,
+                events::ClientToServerEvents::Message {
+                    message: "appl".to_string(),
+                }
+            )),
+            events::ErrorCode::WordExposed
+        );
+    }
+
+    #[test]
+    fn test_close_guesses_after_guessing_the_word() {
+        let mut game_room = test_room(&["host", "player", "other"]);
+        let user_id_drawing = start_drawing_apple(&mut game_room);
+        let user_id_guessing = game_room
+            .users
+            .iter()
+            .find(|user| user.id != user_id_drawing)
+            .unwrap()
+            .id
+            .clone();
+
+        send_event(
+            &mut game_room,
+            &user_id_guessing,
+            events::ClientToServerEvents::Message {
+                message: "apple".to_string(),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            rule_violation(send_event(
+                &mut game_room,
+                &user_id_guessing,
+                events::ClientToServerEvents::Message {
+                    message: "appl".to_string(),
+                }
+            )),
+            events::ErrorCode::WordExposed
+        );
+    }
+
+    #[test]
+    fn test_guessing_the_word() {
+        let mut game_room = test_room(&["host", "player", "other"]);
+        let user_id_drawing = start_drawing_apple(&mut game_room);
+        let user_id_guessing = game_room
+            .users
+            .iter()
+            .find(|user| user.id != user_id_drawing)
+            .unwrap()
+            .id
+            .clone();
+        let guess = events::ClientToServerEvents::Message {
+            message: "Apple".to_string(),
+        };
+        let effects =
+            send_event(&mut game_room, &user_id_guessing, guess.clone()).unwrap();
+        let guesser_score = scoring::guesser_score(
+            game_room.room.draw_time_limit,
+            game_room.room.draw_time_limit,
+            0,
+        );
+        let drawer_score = scoring::drawer_score(
+            game_room.room.draw_time_limit,
+            game_room.room.draw_time_limit,
+        );
+
+        assert_eq!(
+            sent(&effects),
+            vec![
+                events::ServerToClientEvents::AddScore {
+                    user_id: user_id_drawing.clone(),
+                    score: drawer_score,
+                },
+                events::ServerToClientEvents::AddScore {
+                    user_id: user_id_guessing.clone(),
+                    score: guesser_score,
+                },
+                events::ServerToClientEvents::UserGuessed {
+                    user_id: user_id_guessing.clone(),
+                },
+                events::ServerToClientEvents::SystemMessage {
+                    message: format!("{} has guessed the word!", user_id_guessing),
+                },
+                events::ServerToClientEvents::RevealWord {
+                    word: "apple".to_string(),
+                },
+            ]
+        );
+
+        let user = game_room.user(&user_id_guessing).unwrap();
+
+        assert!(user.has_guessed);
+        assert_eq!(user.score, guesser_score);
+        assert_eq!(
+            rule_violation(send_event(&mut game_room, &user_id_guessing, guess)),
+            events::ErrorCode::WordExposed
+        );
+    }
+
+    #[test]
+    fn test_everyone_guessing_ends_the_turn() {
+        let mut game_room = test_room(&["host", "player"]);
+
+        // The game goes on in the last round until everyone has drawn.
+        game_room.room.max_rounds = 1;
+
+        let user_id_drawing = start_drawing_apple(&mut game_room);
+        let user_id_guessing = game_room
+            .users
+            .iter()
+            .find(|user| user.id != user_id_drawing)
+            .unwrap()
+            .id
+            .clone();
+        let effects = send_event(
+            &mut game_room,
+            &user_id_guessing,
+            events::ClientToServerEvents::Message {
+                message: "apple".to_string(),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(current_user_id(&game_room), user_id_guessing);
+        assert!(
+            sent(&effects).contains(&events::ServerToClientEvents::NewTurn {
+                user_id_to_draw: user_id_guessing.clone(),
+            })
+        );
+        assert_eq!(effects[effects.len() - 1..], [Effect::StartTimer]);
+        assert!(effects.contains(&Effect::StopTimer));
+        assert!(game_room
+            .users
+            .iter()
+            .all(|user| !user.has_guessed));
+    }
+
+    #[test]
+    fn test_next_round() {
+        let mut game_room = test_room(&["host", "player"]);
+
+        start_drawing_apple(&mut game_room);
+        set_time_left(&mut game_room, 0);
+        game_room.apply(Command::Tick).unwrap();
+
+        // Both players have drawn once the second turn is up.
+        let user_id_drawing = current_user_id(&game_room);
+
+        pick_word(&mut game_room, &user_id_drawing, "pear").unwrap();
+        set_time_left(&mut game_room, 0);
+
+        let effects = game_room.apply(Command::Tick).unwrap();
+
+        assert!(
+            sent(&effects).contains(&events::ServerToClientEvents::NewRound { round: 2 })
+        );
+        assert!(matches!(
+            game_room.room.state,
+            state::RoomState::Playing {
+                playing_state: state::PlayingState::PickingAWord { .. },
+                current_round: 2,
+                ..
+            }
+        ));
+        assert_eq!(effects.last(), Some(&Effect::StartTimer));
+    }
+
+    #[test]
+    fn test_end_game() {
+        let mut game_room = test_room(&["host", "player"]);
+
+        game_room.room.max_rounds = 1;
+        start_drawing_apple(&mut game_room);
+        set_time_left(&mut game_room, 0);
+
+        // The game only ends once everyone has drawn in the last round.
+        let effects = game_room.apply(Command::Tick).unwrap();
+        let user_id_drawing = current_user_id(&game_room);
+
+        assert!(
+            sent(&effects).contains(&events::ServerToClientEvents::NewTurn {
+                user_id_to_draw: user_id_drawing.clone(),
+            })
+        );
+
+        pick_word(&mut game_room, &user_id_drawing, "pear").unwrap();
+        game_room.users[0].score = 100;
+        set_time_left(&mut game_room, 0);
+
+        let effects = game_room.apply(Command::Tick).unwrap();
+
+        assert_eq!(
+            sent(&effects),
+            vec![
+                events::ServerToClientEvents::Tick { time_left: 0 },
+                events::ServerToClientEvents::EndGame,
+            ]
+        );
+        assert!(effects.contains(&Effect::StopTimer));
+        assert!(!effects.contains(&Effect::StartTimer));
+        assert_eq!(game_room.room.state, state::RoomState::Finished);
+        assert!(game_room
+            .users
+            .iter()
+            .all(|user| user.score == 0 && !user.has_drawn));
+    }
+
+    #[test]
+    fn test_broken_invariants_are_errors() {
+        let mut game_room = test_room(&["host", "player"]);
+
+        {
+            let Room { room, users, .. } = &mut game_room;
+
+            assert_eq!(
+                next_round("a", &mut vec![], users, room),
+                Err(SkribblError::internal("Room is not being played"))
+            );
+            assert_eq!(
+                start_drawing(room, &mut vec![]),
+                Err(SkribblError::internal("Room is not being played"))
+            );
+        }
+
+        start_drawing_apple(&mut game_room);
+
+        let Room { room, users, .. } = &mut game_room;
+
+        assert_eq!(
+            start_drawing(room, &mut vec![]),
+            Err(SkribblError::internal("A word is already being drawn"))
+        );
+        assert_eq!(
+            next_round("a", &mut vec![], users, room),
+            Err(SkribblError::internal(
+                "A new round cannot start before everyone has drawn"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_user_drawing_leaving_passes_the_turn() {
+        let mut game_room = test_room(&["host", "player", "other"]);
+        let user_id_drawing = start_drawing_apple(&mut game_room);
+        let effects = game_room
+            .apply(Command::Leave {
+                user_id: user_id_drawing.clone(),
+            })
+            .unwrap();
+        let user_id_to_draw = current_user_id(&game_room);
+
+        assert_ne!(user_id_to_draw, user_id_drawing);
+        assert_eq!(effects[0], Effect::StopTimer);
+        assert!(effects.contains(&Effect::StartTimer));
+        assert!(sent(&effects)
+            .contains(&events::ServerToClientEvents::NewTurn { user_id_to_draw }));
+        assert_eq!(
+            sent(&effects).last(),
+            Some(&events::ServerToClientEvents::UserLeft {
+                user_id: user_id_drawing,
+            })
+        );
+    }
+
+    #[test]
+    fn test_host_leaving_passes_the_host() {
+        let mut game_room = test_room(&["host", "player"]);
+
+        game_room.user_mut("player").unwrap().is_connected = false;
+        game_room
+            .apply(Command::Join {
+                user: user("other"),
+                only_if_waiting: false,
+            })
+            .unwrap();
+
+        let effects = game_room
+            .apply(Command::Leave {
+                user_id: "host".to_string(),
+            })
+            .unwrap();
+
+        // Users who are still connected are preferred.
+        assert_eq!(game_room.room.host_id, "other");
+        assert_eq!(
+            sent(&effects),
+            vec![
+                events::ServerToClientEvents::NewHost {
+                    user_id: "other".to_string(),
+                },
+                events::ServerToClientEvents::UserLeft {
+                    user_id: "host".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_last_player_left_resets_the_room() {
+        let mut game_room = test_room(&["host", "player"]);
+
+        start_drawing_apple(&mut game_room);
+
+        let effects = game_room
+            .apply(Command::Leave {
+                user_id: "player".to_string(),
+            })
+            .unwrap();
+
+        assert_eq!(game_room.room.state, state::RoomState::Waiting);
+        assert_eq!(
+            effects,
+            vec![
+                Effect::Send {
+                    to: events::WebSocketMessageType::Broadcast {
+                        sender_id: "player".to_string(),
+                    },
+                    event: events::ServerToClientEvents::ResetRoom,
+                },
+                Effect::StopTimer,
+                Effect::Send {
+                    to: events::WebSocketMessageType::Broadcast {
+                        sender_id: "player".to_string(),
+                    },
+                    event: events::ServerToClientEvents::UserLeft {
+                        user_id: "player".to_string(),
+                    },
+                },
+            ]
+        );
+        assert_eq!(
+            game_room.apply(Command::Leave {
+                user_id: "host".to_string(),
+            }),
+            Ok(vec![])
+        );
+        assert_eq!(game_room.room.amount_of_users, 0);
+    }
+
+    #[test]
+    fn test_spectators_leave_quietly() {
+        let mut game_room = test_room(&["host"]);
+        let mut spectator = user("spectator");
+
+        spectator.is_spectator = true;
+        game_room
+            .apply(Command::Join {
+                user: spectator,
+                only_if_waiting: false,
+            })
+            .unwrap();
+
+        assert_eq!(
+            game_room.apply(Command::Leave {
+                user_id: "spectator".to_string(),
+            }),
+            Ok(vec![])
+        );
+        assert!(game_room
+            .apply(Command::Leave {
+                user_id: "spectator".to_string(),
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn test_update_settings() {
+        let mut game_room = test_room(&["host", "player"]);
+        let settings = state::RoomSettings {
+            max_rounds: 5,
+            ..game_room.room.settings()
+        };
+        let update_settings = events::ClientToServerEvents::UpdateSettings {
+            settings: settings.clone(),
+        };
+
+        assert_eq!(
+            rule_violation(send_event(
+                &mut game_room,
+                "player",
+                update_settings.clone()
+            )),
+            events::ErrorCode::NotHost
+        );
+        assert_eq!(
+            send_event(&mut game_room, "host", update_settings.clone()),
+            Ok(vec![Effect::Send {
+                to: events::WebSocketMessageType::Everyone,
+                event: events::ServerToClientEvents::SettingsUpdated {
+                    settings: settings.clone(),
+                },
+            }])
+        );
+        assert_eq!(game_room.room.max_rounds, 5);
+
+        send_event(
+            &mut game_room,
+            "host",
+            events::ClientToServerEvents::StartGame,
+        )
+        .unwrap();
+
+        assert_eq!(
+            rule_violation(send_event(&mut game_room, "host", update_settings)),
+            events::ErrorCode::InvalidRoomState
+        );
+    }
+
+    #[test]
+    fn test_upload_custom_words() {
+        let mut game_room = test_room(&["host", "player"]);
+        let upload_custom_words = events::ClientToServerEvents::UploadCustomWords {
+            words: "apple, pear, plum, fig".to_string(),
+            mode: state::CustomWordsMode::Mix,
+        };
+
+        assert_eq!(
+            rule_violation(send_event(
+                &mut game_room,
+                "host",
+                upload_custom_words.clone()
+            )),
+            events::ErrorCode::RoomNotPrivate
+        );
+
+        game_room.room.visibility = state::Visibility::Private;
+
+        assert_eq!(
+            rule_violation(send_event(
+                &mut game_room,
+                "player",
+                upload_custom_words.clone()
+            )),
+            events::ErrorCode::NotHost
+        );
+        assert_eq!(
+            sent(&send_event(&mut game_room, "host", upload_custom_words).unwrap()),
+            vec![events::ServerToClientEvents::SystemMessage {
+                message: "The host added 4 custom words to the default words".to_string(),
+            }]
+        );
+        assert_eq!(game_room.room.word_source.custom_words.len(), 4);
+    }
+
+    #[test]
+    fn test_select_word_pack() {
+        let mut game_room = test_room(&["host", "player"]);
+
+        assert_eq!(
+            rule_violation(send_event(
+                &mut game_room,
+                "host",
+                events::ClientToServerEvents::SelectWordPack {
+                    category: "nope".to_string(),
+                    language: "nope".to_string(),
+                }
+            )),
+            events::ErrorCode::WordPackNotFound
+        );
+
+        let summary = game_room.word_packs.summaries().remove(0);
+
+        assert!(send_event(
+            &mut game_room,
+            "host",
+            events::ClientToServerEvents::SelectWordPack {
+                category: summary.category,
+                language: summary.language,
+            }
+        )
+        .is_ok());
+        assert!(game_room.room.word_source.pack.is_some());
+    }
+
+    #[test]
+    fn test_catch_up_events() {
+        let mut game_room = test_room(&["host", "player"]);
+        let user_id_drawing = start_drawing_apple(&mut game_room);
+        let drawer = game_room.user(&user_id_drawing).unwrap().clone();
+        let catch_up_events = game_room.catch_up_events(&drawer);
+
+        assert!(matches!(
+            catch_up_events[0],
+            events::ServerToClientEvents::SendGameState { .. }
+        ));
+        assert_eq!(
+            catch_up_events[1],
+            events::ServerToClientEvents::NewWord {
+                word: "apple".to_string(),
+            }
+        );
+    }
+}
@@ -1,15 +1,28 @@
-use rocket::tokio;
-
+pub mod binary_structs;
+pub mod clock;
+pub mod error;
 pub mod events;
 pub mod fairings;
+pub mod game;
+pub mod room;
 pub mod routes;
+pub mod schema;
+pub mod scoring;
+pub mod similarity;
 pub mod state;
 pub mod utils;
+pub mod word_packs;
 
 pub async fn init_rocket(
     rocket: rocket::Rocket<rocket::Build>,
 ) -> rocket::Rocket<rocket::Build> {
     let game_state = state::GameState::default();
+    let word_packs_dir = rocket
+        .figment()
+        .extract_inner::<String>("word_packs_dir")
+        .unwrap_or_else(|_| utils::consts::DEFAULT_WORD_PACKS_DIR.to_string());
+    let word_packs = word_packs::WordPacks::load(&word_packs_dir)
+        .unwrap_or_else(|err| panic!("Failed to load word packs: {}", err));
 
     /* {
         let cloned_game_state = game_state.clone();
@@ -27,17 +40,23 @@ pub async fn init_rocket(
     } */
 
     rocket
-        .mount("/", rocket::routes![routes::index::index_page,])
+        .mount(
+            "/",
+            rocket::routes![
+                routes::index::index_page,
+                routes::word_packs::word_packs_endpoint,
+            ],
+        )
         .mount(
             "/ws",
             rocket::routes![
                 routes::realtime::socket::ws_endpoint,
                 routes::realtime::binary_protocol_version_endpoint,
+                routes::realtime::schema_endpoint,
             ],
         )
         .mount("/dist", rocket::fs::FileServer::from("dist"))
         .attach(fairings::stage_templates())
-        .manage(tokio::sync::broadcast::channel::<events::WebSocketMessage>(1024).0)
-        .manage(tokio::sync::broadcast::channel::<state::TickerMsg>(1024).0)
+        .manage(word_packs)
         .manage(game_state)
 }
@@ -0,0 +1,955 @@
+//! Every room is run by its own task, which owns the room along with its users
+//! and handles the commands sent by their connections one at a time. Rooms never
+//! wait on each other, and the timer of a turn lives in the task of its room.
+//!
+//! The rules themselves are in `game`. The task only feeds them commands and
+//! carries out the effects they return.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use rocket::futures::future::BoxFuture;
+use rocket::tokio;
+use rocket::tokio::sync::{broadcast, mpsc, oneshot};
+
+use crate::error::SkribblError;
+use crate::{clock, events, game, state, utils};
+
+pub enum RoomCommand {
+    /// An event sent by a user, answered with the rule it broke, if any.
+    Event {
+        user_id: String,
+        event: events::ClientToServerEvents,
+        reply: oneshot::Sender<Result<(), SkribblError>>,
+    },
+    /// Adds a user to the room, answered with the events that bring them up to
+    /// speed. Players are only let in while waiting for players if
+    /// `only_if_waiting` is true, i.e., through matchmaking.
+    Join {
+        user: state::User,
+        only_if_waiting: bool,
+        reply: oneshot::Sender<Result<Vec<events::ServerToClientEvents>, SkribblError>>,
+    },
+    /// Gives a user who lost connection their place in the room back, answered
+    /// with the user and the events that bring them up to speed.
+    Resume {
+        resume_token: String,
+        reply: oneshot::Sender<Option<(state::User, Vec<events::ServerToClientEvents>)>>,
+    },
+    /// The connection of a user was closed.
+    Disconnect { user_id: String },
+    /// Removes a user who lost connection unless they have reconnected since.
+    RemoveIfDisconnected {
+        user_id: String,
+        connection_id: String,
+    },
+}
+
+/// Sends commands to the task of a room.
+#[derive(Clone, Debug)]
+pub struct RoomHandle {
+    pub commands: mpsc::Sender<RoomCommand>,
+    /// Kept up to date by the task of the room, so matchmaking does not have to
+    /// ask every room whether it can be joined.
+    pub is_available: Arc<AtomicBool>,
+    /// The events sent to the users of the room.
+    pub messages: broadcast::Sender<events::WebSocketMessage>,
+}
+
+impl RoomHandle {
+    pub fn is_available(&self) -> bool {
+        self.is_available.load(Ordering::Relaxed)
+    }
+
+    /// Receives the events sent to the room from now on. Connections subscribe
+    /// before joining so they miss nothing sent once they are in.
+    pub fn subscribe(&self) -> broadcast::Receiver<events::WebSocketMessage> {
+        self.messages.subscribe()
+    }
+
+    pub async fn send_event(
+        &self,
+        user_id: &str,
+        event: events::ClientToServerEvents,
+    ) -> Result<(), SkribblError> {
+        self.request(|reply| RoomCommand::Event {
+            user_id: user_id.to_string(),
+            event,
+            reply,
+        })
+        .await
+        .unwrap_or_else(|| Err(SkribblError::internal("Room not found")))
+    }
+
+    pub async fn join(
+        &self,
+        user: state::User,
+        only_if_waiting: bool,
+    ) -> Result<Vec<events::ServerToClientEvents>, SkribblError> {
+        self.request(|reply| RoomCommand::Join {
+            user,
+            only_if_waiting,
+            reply,
+        })
+        .await
+        .unwrap_or_else(|| {
+            Err(SkribblError::rule_violation(
+                events::ErrorCode::RoomNotFound,
+                "Room not found",
+            ))
+        })
+    }
+
+    pub async fn resume(
+        &self,
+        resume_token: &str,
+    ) -> Option<(state::User, Vec<events::ServerToClientEvents>)> {
+        self.request(|reply| RoomCommand::Resume {
+            resume_token: resume_token.to_string(),
+            reply,
+        })
+        .await
+        .flatten()
+    }
+
+    /// Waits until the room has been closed, i.e., its last player has left.
+    pub async fn closed(&self) {
+        self.commands.closed().await
+    }
+
+    pub async fn disconnect(&self, user_id: &str) {
+        let _ = self
+            .commands
+            .send(RoomCommand::Disconnect {
+                user_id: user_id.to_string(),
+            })
+            .await;
+    }
+
+    /// Sends the command made by `command` and waits for its reply. Returns
+    /// `None` if the room has been closed.
+    async fn request<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<T>) -> RoomCommand,
+    ) -> Option<T> {
+        let (reply, reply_rx) = oneshot::channel();
+
+        self.commands.send(command(reply)).await.ok()?;
+
+        reply_rx.await.ok()
+    }
+}
+
+/// The one timer of a room, which counts down the time left in a turn once a
+/// second while it is running. Starting it again replaces the running countdown
+/// instead of adding another.
+///
+/// Every start or stop begins a new generation. A wait for the next tick is armed
+/// with the generation it was armed in, and `Ticker::on_tick` drops the tick
+/// unless that generation is still the current one, so a tick that was already
+/// due when the turn changed never counts down the next one.
+pub struct Ticker {
+    clock: Arc<dyn clock::Clock>,
+    generation: u64,
+    /// When the next tick is due, if the ticker is running.
+    next_tick: Option<tokio::time::Duration>,
+}
+
+impl Ticker {
+    pub fn new(clock: Arc<dyn clock::Clock>) -> Self {
+        Self {
+            clock,
+            generation: 0,
+            next_tick: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.next_tick.is_some()
+    }
+
+    /// Starts counting down from the next tick, which is right away.
+    pub fn start(&mut self) {
+        self.generation += 1;
+        self.next_tick = Some(self.clock.now());
+    }
+
+    pub fn stop(&mut self) {
+        self.generation += 1;
+        self.next_tick = None;
+    }
+
+    /// Arms a wait for the next tick, which resolves to the generation it was
+    /// armed in, or never if the ticker is not running.
+    pub fn next_tick(&self) -> BoxFuture<'static, u64> {
+        let Some(next_tick) = self.next_tick else {
+            return Box::pin(std::future::pending());
+        };
+        let generation = self.generation;
+        let sleep = self.clock.sleep_until(next_tick);
+
+        Box::pin(async move {
+            sleep.await;
+
+            generation
+        })
+    }
+
+    /// Counts a tick armed in `generation`, scheduling the one after it. Returns
+    /// false if the ticker has been started again or stopped since.
+    pub fn on_tick(&mut self, generation: u64) -> bool {
+        let Some(next_tick) = self.next_tick else {
+            return false;
+        };
+
+        if generation != self.generation {
+            return false;
+        }
+
+        self.next_tick = Some(
+            next_tick + tokio::time::Duration::from_millis(utils::consts::TICK_INTERVAL),
+        );
+
+        true
+    }
+}
+
+/// Starts the task of `game_room` and adds it to `game_state`.
+pub async fn create(game_room: game::Room, game_state: &state::GameState) -> RoomHandle {
+    let (commands, commands_rx) = mpsc::channel(utils::consts::ROOM_COMMANDS_CAPACITY);
+    let (messages, _) = broadcast::channel(utils::consts::ROOM_MESSAGES_CAPACITY);
+    let handle = RoomHandle {
+        commands: commands.clone(),
+        is_available: Arc::new(AtomicBool::new(game_room.is_available())),
+        messages: messages.clone(),
+    };
+
+    {
+        let mut rooms = game_state.rooms.lock().await;
+
+        for user in &game_room.users {
+            rooms.add_resume_token(user.resume_token.clone(), game_room.room.id.clone());
+        }
+
+        rooms.insert(game_room.room.id.clone(), handle.clone());
+    }
+
+    let actor = RoomActor {
+        game_room,
+        ticker: Ticker::new(game_state.clock.clone()),
+        commands,
+        is_available: handle.is_available.clone(),
+        game_state: game_state.clone(),
+        messages,
+    };
+
+    tokio::spawn(actor.run(commands_rx));
+
+    handle
+}
+
+struct RoomActor {
+    game_room: game::Room,
+    ticker: Ticker,
+    /// Lets the room send commands to itself, e.g., to remove a user once the
+    /// time they have to reconnect is up.
+    commands: mpsc::Sender<RoomCommand>,
+    is_available: Arc<AtomicBool>,
+    game_state: state::GameState,
+    messages: broadcast::Sender<events::WebSocketMessage>,
+}
+
+impl RoomActor {
+    /// Handles commands and ticks until every player has left. The room is then
+    /// closed, which also ends the connections of whoever is still spectating.
+    async fn run(mut self, mut commands_rx: mpsc::Receiver<RoomCommand>) {
+        loop {
+            tokio::select! {
+                command = commands_rx.recv() => {
+                    let Some(command) = command else {
+                        break;
+                    };
+
+                    self.on_command(command);
+                }
+                generation = self.ticker.next_tick() => {
+                    if !self.ticker.on_tick(generation) {
+                        continue;
+                    }
+
+                    if let Err(err) = self.apply(game::Command::Tick) {
+                        eprintln!("{:?}", err);
+
+                        self.ticker.stop();
+                    }
+                }
+            }
+
+            if self.game_room.room.amount_of_users == 0 {
+                break;
+            }
+
+            self.is_available
+                .store(self.game_room.is_available(), Ordering::Relaxed);
+        }
+
+        let _ = self
+            .game_state
+            .rooms_updates
+            .send(state::RoomsUpdate::Remove {
+                room_id: self.game_room.room.id.clone(),
+                resume_tokens: self
+                    .game_room
+                    .users
+                    .iter()
+                    .map(|user| user.resume_token.clone())
+                    .collect(),
+            });
+
+        println!("Closed room {}", self.game_room.room.id);
+    }
+
+    fn on_command(&mut self, command: RoomCommand) {
+        match command {
+            RoomCommand::Event {
+                user_id,
+                event,
+                reply,
+            } => {
+                let _ = reply.send(self.apply(game::Command::Event { user_id, event }));
+            }
+            RoomCommand::Join {
+                user,
+                only_if_waiting,
+                reply,
+            } => {
+                let _ = reply.send(self.on_join(user, only_if_waiting));
+            }
+            RoomCommand::Resume {
+                resume_token,
+                reply,
+            } => {
+                let _ = reply.send(self.on_resume(&resume_token));
+            }
+            RoomCommand::Disconnect { user_id } => {
+                if let Err(err) = self.on_disconnect(&user_id) {
+                    eprintln!("{:?}", err);
+                }
+            }
+            RoomCommand::RemoveIfDisconnected {
+                user_id,
+                connection_id,
+            } => {
+                let has_reconnected = self.game_room.user(&user_id).is_none_or(|user| {
+                    user.is_connected || user.connection_id != connection_id
+                });
+
+                if has_reconnected {
+                    return;
+                }
+
+                if let Err(err) = self.leave(&user_id) {
+                    eprintln!("{:?}", err);
+                }
+            }
+        }
+    }
+
+    /// Applies `command` to the room and carries out what the rules say should
+    /// happen.
+    fn apply(&mut self, command: game::Command) -> Result<(), SkribblError> {
+        for effect in self.game_room.apply(command)? {
+            match effect {
+                game::Effect::Send { to, event } => {
+                    let _ = events::WebSocketMessageBuilder::default()
+                        .r#type(to)
+                        .room_id(self.game_room.room.id.clone())
+                        .event(event)
+                        .build()?
+                        .send(&self.messages);
+                }
+                game::Effect::StartTimer => self.ticker.start(),
+                game::Effect::StopTimer => self.ticker.stop(),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_join(
+        &mut self,
+        user: state::User,
+        only_if_waiting: bool,
+    ) -> Result<Vec<events::ServerToClientEvents>, SkribblError> {
+        self.apply(game::Command::Join {
+            user: user.clone(),
+            only_if_waiting,
+        })?;
+        let _ = self
+            .game_state
+            .rooms_updates
+            .send(state::RoomsUpdate::AddResumeToken {
+                resume_token: user.resume_token.clone(),
+                room_id: self.game_room.room.id.clone(),
+            });
+
+        Ok(self.game_room.catch_up_events(&user))
+    }
+
+    fn on_resume(
+        &mut self,
+        resume_token: &str,
+    ) -> Option<(state::User, Vec<events::ServerToClientEvents>)> {
+        let user = self
+            .game_room
+            .users
+            .iter_mut()
+            .find(|user| user.resume_token == resume_token && !user.is_connected)?;
+
+        user.is_connected = true;
+        user.connection_id = utils::gen_random_id();
+
+        let user = user.clone();
+
+        let _ = events::WebSocketMessageBuilder::default()
+            .r#type(events::WebSocketMessageType::Broadcast {
+                sender_id: user.id.clone(),
+            })
+            .room_id(self.game_room.room.id.clone())
+            .event(events::ServerToClientEvents::SystemMessage {
+                message: format!("{} has reconnected", user.display_name),
+            })
+            .build()
+            .unwrap()
+            .send(&self.messages);
+
+        let catch_up_events = self.game_room.catch_up_events(&user);
+
+        Some((user, catch_up_events))
+    }
+
+    /// Keeps the place of a user who lost connection in the room for
+    /// `utils::consts::RECONNECT_GRACE_PERIOD` seconds before removing them, so
+    /// they can resume their session.
+    fn on_disconnect(&mut self, user_id: &str) -> Result<(), SkribblError> {
+        let user = self
+            .game_room
+            .user_mut(user_id)
+            .ok_or_else(|| SkribblError::internal("User not found"))?;
+
+        // Spectators have no place in the game to keep.
+        if user.is_spectator {
+            return self.leave(user_id);
+        }
+
+        user.is_connected = false;
+
+        let connection_id = user.connection_id.clone();
+        let display_name = user.display_name.clone();
+
+        let _ = events::WebSocketMessageBuilder::default()
+            .room_id(self.game_room.room.id.clone())
+            .r#type(events::WebSocketMessageType::Broadcast {
+                sender_id: user_id.to_string(),
+            })
+            .event(events::ServerToClientEvents::SystemMessage {
+                message: format!("{} has lost connection", display_name),
+            })
+            .build()?
+            .send(&self.messages);
+
+        let commands = self.commands.clone();
+        let user_id = user_id.to_string();
+        let grace_period_over = self.game_state.clock.sleep_until(
+            self.game_state.clock.now()
+                + tokio::time::Duration::from_secs(utils::consts::RECONNECT_GRACE_PERIOD),
+        );
+
+        tokio::spawn(async move {
+            grace_period_over.await;
+
+            let _ = commands
+                .send(RoomCommand::RemoveIfDisconnected {
+                    user_id,
+                    connection_id,
+                })
+                .await;
+        });
+
+        Ok(())
+    }
+
+    /// Removes a user from the room for good. The room is closed once its last
+    /// player is removed.
+    fn leave(&mut self, user_id: &str) -> Result<(), SkribblError> {
+        if let Some(user) = self.game_room.user(user_id) {
+            let _ = self.game_state.rooms_updates.send(
+                state::RoomsUpdate::RemoveResumeToken {
+                    resume_token: user.resume_token.clone(),
+                },
+            );
+        }
+
+        self.apply(game::Command::Leave {
+            user_id: user_id.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::futures::FutureExt;
+
+    use super::*;
+    use crate::word_packs;
+
+    fn user(id: &str, room_id: &str) -> state::User {
+        state::UserBuilder::default()
+            .id(id.to_string())
+            .display_name(id.to_string())
+            .room_id(room_id.to_string())
+            .build()
+            .unwrap()
+    }
+
+    /// Starts the task of a room built by `room` and hosted by a new user.
+    async fn test_room(
+        game_state: &state::GameState,
+        room: &mut state::RoomBuilder,
+    ) -> (RoomHandle, state::User) {
+        let host = user("host", "a");
+        let room = room
+            .id("a".to_string())
+            .host_id(host.id.clone())
+            .build()
+            .unwrap();
+        let handle = create(
+            game::Room::new(room, host.clone(), word_packs::WordPacks::default()),
+            game_state,
+        )
+        .await;
+
+        (handle, host)
+    }
+
+    /// Waits for the next message sent to the room matching `predicate`, giving
+    /// up after a second of real time.
+    async fn wait_for(
+        messages: &mut tokio::sync::broadcast::Receiver<events::WebSocketMessage>,
+        predicate: impl Fn(&events::WebSocketMessage) -> bool,
+    ) -> events::WebSocketMessage {
+        tokio::time::timeout(tokio::time::Duration::from_secs(1), async {
+            loop {
+                let message = messages.recv().await.unwrap();
+
+                if predicate(&message) {
+                    return message;
+                }
+            }
+        })
+        .await
+        .expect("Expected an event to be sent")
+    }
+
+    /// Waits until `predicate` holds for the rooms, which are updated by a task of
+    /// their own, giving up after a second of real time.
+    async fn wait_for_rooms(
+        game_state: &state::GameState,
+        predicate: impl Fn(&state::Rooms) -> bool,
+    ) {
+        tokio::time::timeout(tokio::time::Duration::from_secs(1), async {
+            while !predicate(&*game_state.rooms.lock().await) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("Expected the rooms to be updated")
+    }
+
+    #[rocket::async_test]
+    async fn test_join() {
+        let game_state = state::GameState::default();
+        let (room, _) =
+            test_room(&game_state, state::RoomBuilder::default().max_users(2)).await;
+        let player = user("player", "a");
+
+        assert!(matches!(
+            room.join(player.clone(), true).await.unwrap()[0],
+            events::ServerToClientEvents::SendGameState { .. }
+        ));
+        assert!(!room.is_available());
+        assert!(matches!(
+            room.join(user("late", "a"), false).await,
+            Err(SkribblError::RuleViolation {
+                code: events::ErrorCode::RoomFull,
+                ..
+            })
+        ));
+
+        let mut spectator = user("spectator", "a");
+
+        spectator.is_spectator = true;
+
+        assert!(room.join(spectator.clone(), false).await.is_ok());
+        wait_for_rooms(&game_state, |rooms| {
+            rooms
+                .find_by_resume_token(&spectator.resume_token)
+                .is_some()
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_resume() {
+        let game_state = state::GameState::default();
+        let (room, host) =
+            test_room(&game_state, &mut state::RoomBuilder::default()).await;
+
+        assert!(room.resume(&host.resume_token).await.is_none());
+
+        room.disconnect(&host.id).await;
+
+        let (resumed, catch_up_events) = room.resume(&host.resume_token).await.unwrap();
+
+        assert_eq!(resumed.id, host.id);
+        assert!(resumed.is_connected);
+        assert_ne!(resumed.connection_id, host.connection_id);
+        assert!(matches!(
+            catch_up_events[0],
+            events::ServerToClientEvents::SendGameState { .. }
+        ));
+    }
+
+    #[rocket::async_test]
+    async fn test_whole_game_on_a_manual_clock() {
+        let clock = Arc::new(clock::ManualClock::default());
+        let game_state = state::GameState::with_clock(clock.clone());
+        let (room, host) =
+            test_room(&game_state, state::RoomBuilder::default().max_rounds(2)).await;
+        let mut messages = room.subscribe();
+
+        room.join(user("player", "a"), false)
+            .await
+            .unwrap();
+        room.send_event(&host.id, events::ClientToServerEvents::StartGame)
+            .await
+            .unwrap();
+
+        let mut ticks = vec![];
+
+        // No one picks a word or guesses it, so every turn runs out of time.
+        while let events::ServerToClientEvents::Tick { time_left } =
+            wait_for(&mut messages, |message| {
+                matches!(
+                    message.event,
+                    events::ServerToClientEvents::Tick { .. }
+                        | events::ServerToClientEvents::EndGame
+                )
+            })
+            .await
+            .event
+        {
+            ticks.push(time_left);
+            clock.advance(tokio::time::Duration::from_millis(
+                utils::consts::TICK_INTERVAL,
+            ));
+        }
+
+        let turn = (0..=utils::consts::PICK_WORD_TIME_LIMIT)
+            .rev()
+            .chain((0..=utils::consts::DRAW_IME_LIMIT).rev());
+        let ticks_per_turn = turn.clone().count();
+
+        // Both players draw in each of the two rounds.
+        assert_eq!(
+            ticks,
+            turn.cycle()
+                .take(ticks_per_turn * 4)
+                .collect::<Vec<u8>>()
+        );
+    }
+
+    #[rocket::async_test]
+    async fn test_player_is_removed_once_the_grace_period_is_over() {
+        let clock = Arc::new(clock::ManualClock::default());
+        let game_state = state::GameState::with_clock(clock.clone());
+        let (room, _) = test_room(&game_state, &mut state::RoomBuilder::default()).await;
+        let mut messages = room.subscribe();
+        let player = user("player", "a");
+
+        room.join(player.clone(), false).await.unwrap();
+        room.disconnect(&player.id).await;
+
+        wait_for(&mut messages, |message| {
+            matches!(
+                message.event,
+                events::ServerToClientEvents::SystemMessage { .. }
+            )
+        })
+        .await;
+        clock.advance(tokio::time::Duration::from_secs(
+            utils::consts::RECONNECT_GRACE_PERIOD,
+        ));
+
+        assert_eq!(
+            wait_for(&mut messages, |message| {
+                matches!(message.event, events::ServerToClientEvents::UserLeft { .. })
+            })
+            .await
+            .event,
+            events::ServerToClientEvents::UserLeft { user_id: player.id }
+        );
+        wait_for_rooms(&game_state, |rooms| {
+            rooms
+                .find_by_resume_token(&player.resume_token)
+                .is_none()
+        })
+        .await;
+    }
+
+    #[rocket::async_test]
+    async fn test_room_is_closed_once_its_players_are_gone() {
+        let clock = Arc::new(clock::ManualClock::default());
+        let game_state = state::GameState::with_clock(clock.clone());
+        let (room, host) =
+            test_room(&game_state, &mut state::RoomBuilder::default()).await;
+        let mut messages = room.subscribe();
+        let mut spectator = user("spectator", "a");
+
+        spectator.is_spectator = true;
+
+        room.join(spectator, false).await.unwrap();
+        room.disconnect(&host.id).await;
+
+        wait_for(&mut messages, |message| {
+            matches!(
+                message.event,
+                events::ServerToClientEvents::SystemMessage { .. }
+            )
+        })
+        .await;
+
+        assert!(room.closed().now_or_never().is_none());
+
+        clock.advance(tokio::time::Duration::from_secs(
+            utils::consts::RECONNECT_GRACE_PERIOD,
+        ));
+
+        tokio::time::timeout(tokio::time::Duration::from_secs(1), room.closed())
+            .await
+            .expect("Expected the room to be closed");
+
+        wait_for_rooms(&game_state, |rooms| rooms.get("a").is_none()).await;
+    }
+
+    /// Expects the current phase of the turn to count down from `from` to 1, a
+    /// tick at a time, moving `clock` forward between ticks.
+    async fn count_down(
+        clock: &clock::ManualClock,
+        messages: &mut tokio::sync::broadcast::Receiver<events::WebSocketMessage>,
+        from: u8,
+    ) {
+        for time_left in (1..=from).rev() {
+            if time_left < from {
+                clock.advance(tokio::time::Duration::from_millis(
+                    utils::consts::TICK_INTERVAL,
+                ));
+            }
+
+            assert_eq!(
+                wait_for(messages, |message| {
+                    matches!(message.event, events::ServerToClientEvents::Tick { .. })
+                })
+                .await
+                .event,
+                events::ServerToClientEvents::Tick { time_left }
+            );
+        }
+    }
+
+    #[rocket::async_test]
+    async fn test_ticker() {
+        let clock = Arc::new(clock::ManualClock::default());
+        let mut ticker = Ticker::new(clock.clone());
+        let half_a_tick =
+            tokio::time::Duration::from_millis(utils::consts::TICK_INTERVAL / 2);
+
+        assert!(ticker.next_tick().now_or_never().is_none());
+
+        ticker.start();
+
+        assert_eq!(ticker.next_tick().now_or_never(), Some(1));
+        assert!(ticker.on_tick(1));
+
+        // Armed for the tick due a tick from now, which is about to be replaced.
+        let mut stale_tick = ticker.next_tick();
+
+        clock.advance(half_a_tick);
+        ticker.start();
+        clock.advance(half_a_tick);
+
+        assert_eq!((&mut stale_tick).now_or_never(), Some(1));
+        assert!(!ticker.on_tick(1));
+
+        // The countdown started again half a tick ago, so its first tick is due.
+        assert_eq!(ticker.next_tick().now_or_never(), Some(2));
+        assert!(ticker.on_tick(2));
+        assert!(ticker.next_tick().now_or_never().is_none());
+
+        clock.advance(half_a_tick);
+
+        let tick = ticker.next_tick();
+
+        ticker.stop();
+
+        assert_eq!(tick.now_or_never(), Some(2));
+        assert!(!ticker.on_tick(2));
+        assert!(!ticker.is_running());
+        assert!(ticker.next_tick().now_or_never().is_none());
+    }
+
+    #[rocket::async_test]
+    async fn test_concurrent_turn_transitions() {
+        let clock = Arc::new(clock::ManualClock::default());
+        let game_state = state::GameState::with_clock(clock.clone());
+        let (room, host) =
+            test_room(&game_state, state::RoomBuilder::default().max_rounds(2)).await;
+        let mut messages = room.subscribe();
+        let player = user("player", "a");
+        let is_turn_change = |message: &events::WebSocketMessage| {
+            matches!(
+                message.event,
+                events::ServerToClientEvents::Tick { .. }
+                    | events::ServerToClientEvents::NewTurn { .. }
+                    | events::ServerToClientEvents::EndGame
+            )
+        };
+        let mut turns = 0;
+
+        room.join(player.clone(), false).await.unwrap();
+        room.send_event(&host.id, events::ClientToServerEvents::StartGame)
+            .await
+            .unwrap();
+
+        loop {
+            let mut message = wait_for(&mut messages, is_turn_change).await;
+
+            // The time to draw ran out before the right guess came in.
+            if turns > 0
+                && message.event == (events::ServerToClientEvents::Tick { time_left: 0 })
+            {
+                message = wait_for(&mut messages, is_turn_change).await;
+            }
+
+            let events::ServerToClientEvents::NewTurn { user_id_to_draw } = message.event
+            else {
+                assert_eq!(message.event, events::ServerToClientEvents::EndGame);
+
+                break;
+            };
+            let guesser_id = if user_id_to_draw == host.id {
+                &player.id
+            } else {
+                &host.id
+            };
+            let events::ServerToClientEvents::PickAWord { words_to_pick } =
+                wait_for(&mut messages, |message| {
+                    matches!(
+                        message.event,
+                        events::ServerToClientEvents::PickAWord { .. }
+                    )
+                })
+                .await
+                .event
+            else {
+                unreachable!()
+            };
+
+            count_down(&clock, &mut messages, utils::consts::PICK_WORD_TIME_LIMIT).await;
+
+            // Pick a word as the time to pick one runs out.
+            let (picked, _) = tokio::join!(
+                room.send_event(
+                    &user_id_to_draw,
+                    events::ClientToServerEvents::PickAWord {
+                        word: words_to_pick[0].clone(),
+                    },
+                ),
+                async {
+                    clock.advance(tokio::time::Duration::from_millis(
+                        utils::consts::TICK_INTERVAL,
+                    ))
+                }
+            );
+            let is_word_to_draw = |message: &events::WebSocketMessage| {
+                matches!(message.event, events::ServerToClientEvents::Tick { .. })
+                    || (matches!(
+                        message.event,
+                        events::ServerToClientEvents::NewWord { .. }
+                    ) && message.r#type
+                        == events::WebSocketMessageType::User {
+                            receiver_id: user_id_to_draw.clone(),
+                        })
+            };
+            let mut message = wait_for(&mut messages, is_word_to_draw).await;
+
+            // Only one of the two can start the drawing.
+            if message.event == (events::ServerToClientEvents::Tick { time_left: 0 }) {
+                assert!(picked.is_err());
+
+                message = wait_for(&mut messages, is_word_to_draw).await;
+            }
+
+            let events::ServerToClientEvents::NewWord { word } = message.event else {
+                panic!("Expected the word to draw, got {:?}", message.event);
+            };
+
+            if picked.is_ok() {
+                assert_eq!(word, words_to_pick[0]);
+            }
+
+            count_down(&clock, &mut messages, utils::consts::DRAW_IME_LIMIT).await;
+
+            // Guess the word as the time to draw runs out.
+            let (guessed, _) = tokio::join!(
+                room.send_event(
+                    guesser_id,
+                    events::ClientToServerEvents::Message { message: word },
+                ),
+                async {
+                    clock.advance(tokio::time::Duration::from_millis(
+                        utils::consts::TICK_INTERVAL,
+                    ))
+                }
+            );
+
+            assert_eq!(guessed, Ok(()));
+
+            turns += 1;
+        }
+
+        assert_eq!(turns, 4);
+
+        // The game is over, so nothing is left to count down.
+        clock.advance(tokio::time::Duration::from_millis(
+            utils::consts::TICK_INTERVAL * 10,
+        ));
+
+        // The room answers commands in order, so it has handled everything sent
+        // before this join by the time it turns the late player away.
+        assert_eq!(
+            room.join(user("late", "a"), false).await,
+            Err(SkribblError::rule_violation(
+                events::ErrorCode::RoomNotAvailable,
+                "Room is not available",
+            ))
+        );
+
+        while let Ok(message) = messages.try_recv() {
+            assert!(!matches!(
+                message.event,
+                events::ServerToClientEvents::Tick { .. }
+            ));
+        }
+    }
+}
@@ -1,2 +1,3 @@
 pub mod index;
 pub mod realtime;
+pub mod word_packs;
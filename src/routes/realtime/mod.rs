@@ -1,8 +1,27 @@
-use crate::utils::consts::BINARY_PROTOCOL_VERSION;
+use crate::schema;
+use crate::utils::consts::{LATEST_BINARY_PROTOCOL_VERSION, MIN_BINARY_PROTOCOL_VERSION};
 
 pub mod socket;
 
+/// The range of versions of the binary protocol the server supports. Clients state
+/// which ones they support when connecting, and the highest common one is used.
+#[derive(rocket::serde::Serialize)]
+pub struct BinaryProtocolVersionRange {
+    pub min: u8,
+    pub max: u8,
+}
+
 #[rocket::get("/binary-protocol-version")]
-pub fn binary_protocol_version_endpoint() -> rocket::serde::json::Json<u8> {
-    rocket::serde::json::Json(BINARY_PROTOCOL_VERSION)
+pub fn binary_protocol_version_endpoint(
+) -> rocket::serde::json::Json<BinaryProtocolVersionRange> {
+    rocket::serde::json::Json(BinaryProtocolVersionRange {
+        min: MIN_BINARY_PROTOCOL_VERSION,
+        max: LATEST_BINARY_PROTOCOL_VERSION,
+    })
+}
+
+/// Every event of the protocol with its id and fields.
+#[rocket::get("/schema")]
+pub fn schema_endpoint() -> rocket::serde::json::Json<schema::ProtocolSchema> {
+    rocket::serde::json::Json(schema::ProtocolSchema::default())
 }
@@ -1,4 +1,5 @@
-use crate::{events, state, utils};
+use crate::error::SkribblError;
+use crate::{events, game, room, state, utils, word_packs};
 use rocket::{
     futures::{SinkExt, StreamExt},
     tokio,
@@ -13,6 +14,20 @@ pub enum WsEndpointMode {
     Play,
     #[field(value = "create")]
     Create,
+    /// Watch a room without playing.
+    #[field(value = "spectate")]
+    Spectate,
+}
+
+/// How events are sent over the connection.
+#[derive(rocket::FromFormField, PartialEq)]
+pub enum WsEndpointFormat {
+    #[field(value = "binary")]
+    Binary,
+    /// Tagged JSON in text frames, e.g., for driving the game from the browser
+    /// devtools or `websocat`.
+    #[field(value = "json")]
+    Json,
 }
 
 #[derive(rocket::FromForm)]
@@ -23,15 +38,219 @@ pub struct WsEndpointParams {
     #[field(name = "roomId")]
     pub room_id: String,
     pub mode: WsEndpointMode,
+    /// The resume token of a user who lost connection and wants their place in
+    /// the room back.
+    pub resume: Option<String>,
+    /// The versions of the binary protocol the client supports, e.g.,
+    /// `protocolVersions=1&protocolVersions=2`.
+    #[field(name = "protocolVersions")]
+    pub protocol_versions: Vec<u8>,
+    /// Defaults to binary. The versions of the binary protocol are ignored when
+    /// this is `json`.
+    pub format: Option<WsEndpointFormat>,
+}
+
+/// Tells the client why it could not join a room and closes the connection.
+async fn reject(
+    sink: &mut rocket::futures::stream::SplitSink<ws::stream::DuplexStream, ws::Message>,
+    connect_error: events::ServerToClientEvents,
+    format: events::WireFormat,
+) -> ws::result::Result<()> {
+    sink.send(connect_error.to_message(format).unwrap())
+        .await?;
+    sink.close().await
+}
+
+async fn send_events(
+    sink: &mut rocket::futures::stream::SplitSink<ws::stream::DuplexStream, ws::Message>,
+    events: Vec<events::ServerToClientEvents>,
+    format: events::WireFormat,
+) -> ws::result::Result<()> {
+    for event in events {
+        sink.send(event.to_message(format).unwrap())
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Gives a user who lost connection their place in the room back, along with the
+/// current turn. Returns the user, their room and its events, or `None` if there
+/// is no session to resume.
+async fn resume_session(
+    resume_token: &str,
+    game_state: &state::GameState,
+    sink: &mut rocket::futures::stream::SplitSink<ws::stream::DuplexStream, ws::Message>,
+    format: events::WireFormat,
+) -> ws::result::Result<
+    Option<(
+        state::User,
+        room::RoomHandle,
+        tokio::sync::broadcast::Receiver<events::WebSocketMessage>,
+    )>,
+> {
+    let room = game_state
+        .rooms
+        .lock()
+        .await
+        .find_by_resume_token(resume_token)
+        .cloned();
+    let resumed = match room {
+        Some(room) => {
+            let messages = room.subscribe();
+
+            room.resume(resume_token)
+                .await
+                .map(|resumed| (resumed, room, messages))
+        }
+        None => None,
+    };
+    let Some(((user, catch_up_events), room, messages)) = resumed else {
+        reject(
+            sink,
+            events::ServerToClientEvents::ConnectError {
+                code: events::ErrorCode::SessionExpired,
+                message: Some("Session has expired".to_string()),
+            },
+            format,
+        )
+        .await?;
+
+        return Ok(None);
+    };
+
+    send_events(sink, catch_up_events, format).await?;
+
+    Ok(Some((user, room, messages)))
+}
+
+/// Adds `user` to the room with the id `user.room_id`. Returns the room and its
+/// events, or `None` if the user could not join it.
+async fn join_room(
+    user: &state::User,
+    game_state: &state::GameState,
+    sink: &mut rocket::futures::stream::SplitSink<ws::stream::DuplexStream, ws::Message>,
+    format: events::WireFormat,
+) -> ws::result::Result<
+    Option<(
+        room::RoomHandle,
+        tokio::sync::broadcast::Receiver<events::WebSocketMessage>,
+    )>,
+> {
+    let room = game_state
+        .rooms
+        .lock()
+        .await
+        .get(&user.room_id)
+        .cloned();
+    let joined = match room {
+        Some(room) => {
+            let messages = room.subscribe();
+
+            room.join(user.clone(), false)
+                .await
+                .map(|catch_up_events| (room, messages, catch_up_events))
+        }
+        None => Err(SkribblError::rule_violation(
+            events::ErrorCode::RoomNotFound,
+            "Room not found",
+        )),
+    };
+
+    match joined {
+        Ok((room, messages, catch_up_events)) => {
+            send_events(sink, catch_up_events, format).await?;
+
+            Ok(Some((room, messages)))
+        }
+        Err(err) => {
+            reject(sink, err.to_connect_error(), format).await?;
+
+            Ok(None)
+        }
+    }
+}
+
+/// Adds a player to any room waiting for players. Returns the player, their room,
+/// its events and the events that bring them up to speed, or `None` if no room
+/// would have them.
+async fn find_room(
+    display_name: &str,
+    game_state: &state::GameState,
+) -> Option<(
+    state::User,
+    room::RoomHandle,
+    tokio::sync::broadcast::Receiver<events::WebSocketMessage>,
+    Vec<events::ServerToClientEvents>,
+)> {
+    let available_rooms = game_state.rooms.lock().await.available();
+
+    for (room_id, room) in available_rooms {
+        let user = state::UserBuilder::default()
+            .id(utils::gen_random_id())
+            .display_name(display_name.to_string())
+            .room_id(room_id)
+            .build()
+            .unwrap();
+
+        let messages = room.subscribe();
+
+        // The room may have filled up or started since it was found.
+        if let Ok(catch_up_events) = room.join(user.clone(), true).await {
+            return Some((user, room, messages, catch_up_events));
+        }
+    }
+
+    None
+}
+
+/// Creates a room hosted by a new user and starts its task. Returns the host,
+/// the room, its events and the events that tell the host about it.
+async fn create_room(
+    display_name: String,
+    visibility: state::Visibility,
+    game_state: &state::GameState,
+    word_packs: &word_packs::WordPacks,
+) -> (
+    state::User,
+    room::RoomHandle,
+    tokio::sync::broadcast::Receiver<events::WebSocketMessage>,
+    Vec<events::ServerToClientEvents>,
+) {
+    let new_room_id = utils::gen_random_id();
+    let new_user_id = utils::gen_random_id();
+    let room = state::RoomBuilder::default()
+        .id(new_room_id.clone())
+        .host_id(new_user_id.clone())
+        .visibility(visibility)
+        .build()
+        .unwrap();
+    let user = state::UserBuilder::default()
+        .id(new_user_id)
+        .display_name(display_name)
+        .room_id(new_room_id)
+        .build()
+        .unwrap();
+    let catch_up_events = vec![events::ServerToClientEvents::SendGameState {
+        room: Box::new(room.clone()),
+        user: user.clone(),
+        resume_token: user.resume_token.clone(),
+        users_in_room: vec![user.clone()],
+    }];
+    let handle = room::create(
+        game::Room::new(room, user.clone(), word_packs.clone()),
+        game_state,
+    )
+    .await;
+    let messages = handle.subscribe();
+
+    (user, handle, messages, catch_up_events)
 }
 
 #[rocket::get("/?<params..>")]
 pub async fn ws_endpoint<'st>(
     game_state: &'st rocket::State<state::GameState>,
-    server_messages: &'st rocket::State<
-        tokio::sync::broadcast::Sender<events::WebSocketMessage>,
-    >,
-    ticker_msg: &'st rocket::State<tokio::sync::broadcast::Sender<state::TickerMsg>>,
+    word_packs: &'st rocket::State<word_packs::WordPacks>,
     params: Result<WsEndpointParams, rocket::form::Errors<'st>>,
     ws: ws::WebSocket,
 ) -> ws::Channel<'st> {
@@ -41,229 +260,106 @@ pub async fn ws_endpoint<'st>(
     ws.channel(move |duplex: ws::stream::DuplexStream| {
         Box::pin(async move {
             let (mut sink, stream) = duplex.split();
-            let room_id: String;
-            let user_id: String;
+            let format = match &params {
+                Ok(params) if params.format == Some(WsEndpointFormat::Json) => {
+                    Some(events::WireFormat::Json)
+                }
+                Ok(params) => events::BinaryProtocolVersion::negotiate(&params.protocol_versions)
+                    .map(events::WireFormat::Binary),
+                Err(_) => Some(events::WireFormat::default()),
+            };
+            let Some(format) = format else {
+                reject(
+                    &mut sink,
+                    events::ServerToClientEvents::ConnectError {
+                        code: events::ErrorCode::UnsupportedProtocolVersion,
+                        message: Some("Unsupported binary protocol version".to_string()),
+                    },
+                    events::WireFormat::default(),
+                )
+                .await?;
+
+                return Ok(());
+            };
 
             match params {
                 Ok(params) => {
-                    match params.mode {
-                        WsEndpointMode::Play => {
-                            if params.room_id.is_empty() {
-                                let mut rooms = game_state.rooms.lock().await;
-                                let mut users = game_state.users.lock().await;
-
-                                if let Some(available_room) = rooms.iter_mut().find(|room| {
-                                    room.state == state::RoomState::Waiting
-                                        && room.amount_of_users < room.max_users
-                                }) {
-                                    let new_user_id = utils::gen_random_id();
-                                    let user = state::UserBuilder::default()
-                                        .id(new_user_id.clone())
-                                        .display_name(params.display_name)
-                                        .room_id(available_room.id.clone())
-                                        .build()
-                                        .unwrap();
-
-                                    users.push(user.clone());
-                                    available_room.amount_of_users += 1;
-
-                                    sink.send(
-                                        ws::Message::Binary(
-                                            events::ServerToClientEvents::SendGameState {
-                                                room: available_room.clone(),
-                                                user: user.clone(),
-                                                users_in_room: users
-                                                    .iter()
-                                                    .filter(|user| {
-                                                        user.room_id == available_room.id
-                                                    })
-                                                    .cloned()
-                                                    .collect::<Vec<state::User>>()
-                                            }
-                                            .try_into()
-                                            .unwrap()
-                                        )
-                                    ).await?;
-
-                                    let _ = events::WebSocketMessageBuilder::default()
-                                        .r#type(events::WebSocketMessageType::Broadcast { sender_id: new_user_id.clone() })
-                                        .room_id(available_room.id.clone())
-                                        .message(ws::Message::Binary(events::ServerToClientEvents::UserJoined { user }.try_into().unwrap()))
-                                        .build()
-                                        .unwrap().send(server_messages);
-
-                                    room_id = available_room.id.clone();
-                                    user_id = new_user_id;
-                                } else {
-                                    let new_room_id = utils::gen_random_id();
-                                    let new_user_id = utils::gen_random_id();
-                                    let room = state::RoomBuilder::default()
-                                        .id(new_room_id.clone())
-                                        .host_id(new_user_id.clone())
-                                        .build()
-                                        .unwrap();
-                                    let user = state::UserBuilder::default()
-                                        .id(new_user_id.clone())
-                                        .display_name(params.display_name)
-                                        .room_id(new_room_id.clone())
-                                        .build()
-                                        .unwrap();
-
-                                    rooms.push(room.clone());
-                                    users.push(user.clone());
-
-                                    sink.send(
-                                        ws::Message::Binary(
-                                            events::ServerToClientEvents::SendGameState {
-                                                room,
-                                                user: user.clone(),
-                                                users_in_room: vec![user]
-                                            }
-                                            .try_into()
-                                            .unwrap()
-                                        )
-                                    ).await?;
-
-                                    room_id = new_room_id;
-                                    user_id = new_user_id;
-                                };
-                            } else {
-                                let mut rooms = game_state.rooms.lock().await;
-                                let Some(room) = rooms.iter_mut().find(|room| {
-                                    room.id == params.room_id
-                                }) else {
-                                    sink.send(
-                                        ws::Message::Binary(events::ServerToClientEvents::ConnectError {
-                                            message: "Room not found".to_string(),
-                                        }
-                                        .try_into()
-                                        .unwrap()),
-                                    )
-                                    .await?;
-                                    sink.close().await?;
-                                    return Ok(());
-                                };
+                    let (user, room, messages) = if let Some(resume_token) = params.resume {
+                        let Some(resumed) =
+                            resume_session(&resume_token, game_state, &mut sink, format).await?
+                        else {
+                            return Ok(());
+                        };
 
-                                if room.state != state::RoomState::Waiting {
-                                    sink.send(
-                                        ws::Message::Binary(events::ServerToClientEvents::ConnectError {
-                                            message: "Room is not available".to_string(),
+                        resumed
+                    } else {
+                        match params.mode {
+                            WsEndpointMode::Play if params.room_id.is_empty() => {
+                                let (user, room, messages, catch_up_events) =
+                                    match find_room(&params.display_name, game_state).await {
+                                        Some(found) => found,
+                                        None => {
+                                            create_room(
+                                                params.display_name,
+                                                state::Visibility::Public,
+                                                game_state,
+                                                word_packs,
+                                            )
+                                            .await
                                         }
-                                        .try_into()
-                                        .unwrap())
-                                    )
-                                    .await?;
-                                    sink.close().await?;
-                                    return Ok(());
-                                }
+                                    };
 
-                                if room.amount_of_users == room.max_users {
-                                    sink.send(
-                                        ws::Message::Binary(events::ServerToClientEvents::ConnectError {
-                                            message: "Room is full".to_string(),
-                                        }
-                                        .try_into()
-                                        .unwrap()),
-                                    )
-                                    .await?;
-                                    sink.close().await?;
-                                    return Ok(());
-                                }
+                                send_events(&mut sink, catch_up_events, format).await?;
 
-                                let new_user_id = utils::gen_random_id();
+                                (user, room, messages)
+                            }
+                            WsEndpointMode::Play | WsEndpointMode::Spectate => {
                                 let user = state::UserBuilder::default()
-                                    .id(new_user_id.clone())
+                                    .id(utils::gen_random_id())
                                     .display_name(params.display_name)
-                                    .room_id(room.id.clone())
+                                    .room_id(params.room_id)
+                                    .is_spectator(matches!(params.mode, WsEndpointMode::Spectate))
                                     .build()
                                     .unwrap();
-                                let mut users = game_state.users.lock().await;
-
-                                users.push(user.clone());
-                                room.amount_of_users += 1;
-
-                                sink.send(
-                                    ws::Message::Binary(
-                                        events::ServerToClientEvents::SendGameState {
-                                            room: room.clone(),
-                                            user: user.clone(),
-                                            users_in_room: users
-                                                .iter()
-                                                .filter(|user| {
-                                                    user.room_id == room.id
-                                                })
-                                                .cloned()
-                                                .collect::<Vec<state::User>>()
-                                        }
-                                        .try_into()
-                                        .unwrap()
-                                    )
-                                ).await?;
-
-                                let _ = events::WebSocketMessageBuilder::default()
-                                    .r#type(events::WebSocketMessageType::Broadcast { sender_id: new_user_id.clone() })
-                                    .room_id(room.id.clone())
-                                    .message(ws::Message::Binary(events::ServerToClientEvents::UserJoined { user }.try_into().unwrap()))
-                                    .build()
-                                    .unwrap().send(server_messages);
+                                let Some((room, messages)) =
+                                    join_room(&user, game_state, &mut sink, format).await?
+                                else {
+                                    return Ok(());
+                                };
 
-                                room_id = room.id.clone();
-                                user_id = new_user_id;
+                                (user, room, messages)
                             }
-                        }
-                        WsEndpointMode::Create => {
-                            let new_room_id = utils::gen_random_id();
-                            let new_user_id = utils::gen_random_id();
-                            let room = state::RoomBuilder::default()
-                                .id(new_room_id.clone())
-                                .host_id(new_user_id.clone())
-                                .visibility(state::Visibility::Private)
-                                .build()
-                                .unwrap();
-                            let user = state::UserBuilder::default()
-                                .id(new_user_id.clone())
-                                .display_name(params.display_name)
-                                .room_id(new_room_id.clone())
-                                .build()
-                                .unwrap();
-                            let mut rooms = game_state.rooms.lock().await;
-                            let mut users = game_state.users.lock().await;
-
-                            rooms.push(room.clone());
-                            users.push(user.clone());
-
-                            sink.send(
-                                ws::Message::Binary(
-                                    events::ServerToClientEvents::SendGameState {
-                                        room,
-                                        user: user.clone(),
-                                        users_in_room: vec![user]
-                                    }
-                                    .try_into()
-                                    .unwrap()
+                            WsEndpointMode::Create => {
+                                let (user, room, messages, catch_up_events) = create_room(
+                                    params.display_name,
+                                    state::Visibility::Private,
+                                    game_state,
+                                    word_packs,
                                 )
-                            ).await?;
+                                .await;
 
-                            room_id = new_room_id;
-                            user_id = new_user_id;
+                                send_events(&mut sink, catch_up_events, format).await?;
+
+                                (user, room, messages)
+                            }
                         }
-                    }
+                    };
 
                     // We wrap in Arc so many can use this sink.
                     let sink = std::sync::Arc::new(rocket::futures::lock::Mutex::new(sink));
                     let reader = reader::create_websocket_reader(
-                        room_id.clone(),
-                        user_id.clone(),
-                        game_state,
+                        user.room_id.clone(),
+                        user.id.clone(),
+                        user.is_spectator,
+                        room.clone(),
                         stream,
-                        server_messages,
-                        ticker_msg
+                        format
                     );
                     let writer = writer::create_websocket_writer(
-                        room_id.clone(),
-                        user_id.clone(),
+                        user.id.clone(),
+                        format,
                         sink.clone(),
-                        server_messages
+                        messages
                     );
 
                     tokio::select! {
@@ -275,22 +371,25 @@ pub async fn ws_endpoint<'st>(
                         }
                     }
 
+                    // Whichever side ended the connection, the room is told so.
+                    room.disconnect(&user.id).await;
+
                     println!("WebSocket connection closed");
 
                     Ok(())
                 }
                 Err(_) => {
-                    sink.send(
-                        ws::Message::Binary(events::ServerToClientEvents::ConnectError {
-                            message: "Display name is required and must be between 3 and 20 characters long".to_string(),
-                        }
-                        .try_into()
-                        .unwrap()),
+                    reject(
+                        &mut sink,
+                        events::ServerToClientEvents::ConnectError {
+                            code: events::ErrorCode::InvalidParams,
+                            message: Some("Display name is required and must be between 3 and 20 characters long".to_string()),
+                        },
+                        format,
                     )
                     .await?;
-                    sink.close().await?;
 
-                    return Ok(());
+                    Ok(())
                 }
             }
         })
@@ -1,30 +1,37 @@
 use rocket::futures::SinkExt;
+use rocket::tokio::sync::broadcast;
 
 use crate::events;
 
+/// Sends the events of a room meant for a user to them until the connection is
+/// closed.
 pub async fn create_websocket_writer(
-    room_id: String,
     user_id: String,
+    format: events::WireFormat,
     sink: std::sync::Arc<
         rocket::futures::lock::Mutex<
             rocket::futures::stream::SplitSink<ws::stream::DuplexStream, ws::Message>,
         >,
     >,
-    server_messages: &rocket::State<
-        rocket::tokio::sync::broadcast::Sender<events::WebSocketMessage>,
-    >,
+    mut server_messages: broadcast::Receiver<events::WebSocketMessage>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mut server_messages_rx = server_messages.subscribe();
+    loop {
+        let server_message = match server_messages.recv().await {
+            Ok(server_message) => server_message,
+            // A connection too slow to keep up skips what it missed instead of
+            // holding back the room.
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                eprintln!("Connection of {} skipped {} events", user_id, skipped);
 
-    while let Some(server_message) = server_messages_rx.recv().await.ok() {
-        match server_message.r#type {
-            events::WebSocketMessageType::Everyone => {
-                if room_id != server_message.room_id {
-                    continue;
-                }
+                continue;
             }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        match server_message.r#type {
+            events::WebSocketMessageType::Everyone => {}
             events::WebSocketMessageType::Broadcast { sender_id } => {
-                if sender_id == user_id || room_id != server_message.room_id {
+                if sender_id == user_id {
                     continue;
                 }
             }
@@ -35,10 +42,16 @@ pub async fn create_websocket_writer(
             }
         }
 
-        sink.lock()
-            .await
-            .send(server_message.message)
-            .await?;
+        let message = match server_message.event.to_message(format) {
+            Ok(message) => message,
+            Err(err) => {
+                eprintln!("Could not encode event: {}", err);
+
+                continue;
+            }
+        };
+
+        sink.lock().await.send(message).await?;
     }
 
     Ok(())
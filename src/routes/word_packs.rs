@@ -0,0 +1,8 @@
+use crate::word_packs;
+
+#[rocket::get("/word-packs")]
+pub fn word_packs_endpoint(
+    word_packs: &rocket::State<word_packs::WordPacks>,
+) -> rocket::serde::json::Json<Vec<word_packs::WordPackSummary>> {
+    rocket::serde::json::Json(word_packs.summaries())
+}
@@ -0,0 +1,432 @@
+//! A description of every event of the protocol, served at `/ws/schema` so clients
+//! and other tools can generate their code from it instead of copying the ids of
+//! the events by hand.
+//!
+//! Fields are listed in the order they are sent in the binary protocol, and are
+//! named as in the JSON protocol.
+
+use crate::utils::consts::{LATEST_BINARY_PROTOCOL_VERSION, MIN_BINARY_PROTOCOL_VERSION};
+
+#[derive(rocket::serde::Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+    #[serde(rename = "clientToServer")]
+    ClientToServer,
+    #[serde(rename = "serverToClient")]
+    ServerToClient,
+}
+
+/// The type of a field. Structs, lists and `roomSettings` are described in the
+/// README.
+#[derive(rocket::serde::Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FieldType {
+    #[serde(rename = "u8")]
+    U8,
+    /// Big endian.
+    #[serde(rename = "u16")]
+    U16,
+    /// Big endian.
+    #[serde(rename = "f64")]
+    F64,
+    /// UTF-8.
+    #[serde(rename = "string")]
+    String,
+    /// A `u8` from `events::ErrorCode`.
+    #[serde(rename = "errorCode")]
+    ErrorCode,
+    /// A `u8`, 0 for `mix` and 1 for `replace`.
+    #[serde(rename = "customWordsMode")]
+    CustomWordsMode,
+    /// Five `u8` fields in the binary protocol, one for each setting.
+    #[serde(rename = "roomSettings")]
+    RoomSettings,
+    #[serde(rename = "room")]
+    Room,
+    #[serde(rename = "user")]
+    User,
+    #[serde(rename = "user[]")]
+    Users,
+    #[serde(rename = "string[]")]
+    Strings,
+    #[serde(rename = "canvasAction[]")]
+    CanvasActions,
+    /// Delta-encoded, 4 bytes per point.
+    #[serde(rename = "strokePoint[]")]
+    StrokePoints,
+}
+
+#[derive(rocket::serde::Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct FieldSchema {
+    pub name: &'static str,
+    #[serde(rename = "type")]
+    pub r#type: FieldType,
+    /// Optional fields are left out of binary events and are `null` in JSON ones.
+    pub optional: bool,
+}
+
+#[derive(rocket::serde::Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct EventSchema {
+    pub id: u8,
+    /// The `type` of the event in the JSON protocol.
+    pub name: &'static str,
+    pub direction: Direction,
+    pub fields: Vec<FieldSchema>,
+}
+
+#[derive(rocket::serde::Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct ProtocolSchema {
+    #[serde(rename = "minBinaryProtocolVersion")]
+    pub min_binary_protocol_version: u8,
+    #[serde(rename = "maxBinaryProtocolVersion")]
+    pub max_binary_protocol_version: u8,
+    pub events: Vec<EventSchema>,
+}
+
+impl Default for ProtocolSchema {
+    fn default() -> Self {
+        Self {
+            min_binary_protocol_version: MIN_BINARY_PROTOCOL_VERSION,
+            max_binary_protocol_version: LATEST_BINARY_PROTOCOL_VERSION,
+            events: client_to_server_events()
+                .into_iter()
+                .chain(server_to_client_events())
+                .collect(),
+        }
+    }
+}
+
+fn field(name: &'static str, r#type: FieldType) -> FieldSchema {
+    FieldSchema {
+        name,
+        r#type,
+        optional: false,
+    }
+}
+
+fn optional_field(name: &'static str, r#type: FieldType) -> FieldSchema {
+    FieldSchema {
+        optional: true,
+        ..field(name, r#type)
+    }
+}
+
+fn event(
+    id: u8,
+    name: &'static str,
+    direction: Direction,
+    fields: Vec<FieldSchema>,
+) -> EventSchema {
+    EventSchema {
+        id,
+        name,
+        direction,
+        fields,
+    }
+}
+
+/// The events of `events::ClientToServerEvents`.
+pub fn client_to_server_events() -> Vec<EventSchema> {
+    use Direction::ClientToServer as D;
+    use FieldType as T;
+
+    vec![
+        event(0, "startGame", D, vec![]),
+        event(1, "pickAWord", D, vec![field("word", T::String)]),
+        event(2, "pointerDown", D, vec![]),
+        event(
+            3,
+            "pointerMove",
+            D,
+            vec![field("x", T::F64), field("y", T::F64)],
+        ),
+        event(4, "pointerUp", D, vec![]),
+        event(5, "pointerLeave", D, vec![]),
+        event(6, "changeColor", D, vec![field("color", T::String)]),
+        event(7, "message", D, vec![field("message", T::String)]),
+        event(
+            8,
+            "updateSettings",
+            D,
+            vec![field("settings", T::RoomSettings)],
+        ),
+        event(
+            9,
+            "uploadCustomWords",
+            D,
+            vec![
+                field("mode", T::CustomWordsMode),
+                field("words", T::String),
+            ],
+        ),
+        event(
+            10,
+            "selectWordPack",
+            D,
+            vec![
+                field("category", T::String),
+                field("language", T::String),
+            ],
+        ),
+        event(11, "strokeBatch", D, vec![field("points", T::StrokePoints)]),
+    ]
+}
+
+/// The events of `events::ServerToClientEvents`.
+pub fn server_to_client_events() -> Vec<EventSchema> {
+    use Direction::ServerToClient as D;
+    use FieldType as T;
+
+    vec![
+        event(
+            0,
+            "error",
+            D,
+            vec![
+                field("code", T::ErrorCode),
+                optional_field("message", T::String),
+            ],
+        ),
+        event(
+            1,
+            "connectError",
+            D,
+            vec![
+                field("code", T::ErrorCode),
+                optional_field("message", T::String),
+            ],
+        ),
+        event(2, "userJoined", D, vec![field("user", T::User)]),
+        event(3, "userLeft", D, vec![field("userId", T::String)]),
+        event(4, "startGame", D, vec![]),
+        event(5, "pickAWord", D, vec![field("wordsToPick", T::Strings)]),
+        event(6, "endGame", D, vec![]),
+        event(7, "resetRoom", D, vec![]),
+        event(8, "newTurn", D, vec![field("userIdToDraw", T::String)]),
+        event(9, "newWord", D, vec![field("word", T::String)]),
+        event(10, "newRound", D, vec![field("round", T::U8)]),
+        event(11, "newHost", D, vec![field("userId", T::String)]),
+        event(12, "pointerDown", D, vec![]),
+        event(
+            13,
+            "pointerMove",
+            D,
+            vec![field("x", T::F64), field("y", T::F64)],
+        ),
+        event(14, "pointerUp", D, vec![]),
+        event(15, "pointerLeave", D, vec![]),
+        event(16, "changeColor", D, vec![field("color", T::String)]),
+        event(
+            17,
+            "sendGameState",
+            D,
+            vec![
+                field("room", T::Room),
+                field("user", T::User),
+                field("usersInRoom", T::Users),
+                field("resumeToken", T::String),
+            ],
+        ),
+        event(
+            18,
+            "message",
+            D,
+            vec![
+                field("userId", T::String),
+                field("message", T::String),
+            ],
+        ),
+        event(
+            19,
+            "addScore",
+            D,
+            vec![field("userId", T::String), field("score", T::U16)],
+        ),
+        event(20, "tick", D, vec![field("timeLeft", T::U8)]),
+        event(21, "userGuessed", D, vec![field("userId", T::String)]),
+        event(22, "systemMessage", D, vec![field("message", T::String)]),
+        event(23, "revealWord", D, vec![field("word", T::String)]),
+        event(24, "hint", D, vec![field("word", T::String)]),
+        event(25, "closeGuess", D, vec![field("guess", T::String)]),
+        event(
+            26,
+            "settingsUpdated",
+            D,
+            vec![field("settings", T::RoomSettings)],
+        ),
+        event(
+            27,
+            "canvasSnapshot",
+            D,
+            vec![field("actions", T::CanvasActions)],
+        ),
+        event(28, "strokeBatch", D, vec![field("points", T::StrokePoints)]),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{events, state};
+
+    /// Checks that `schema` has the id, the JSON name and the fields of `event`.
+    fn assert_matches_schema(
+        schema: &[EventSchema],
+        id: u8,
+        event: impl rocket::serde::Serialize,
+    ) {
+        let json = serde_json::to_value(event).unwrap();
+        let json = json.as_object().unwrap();
+        let event_schema = schema.iter().find(|e| e.id == id).unwrap();
+        let mut fields: Vec<&str> = json
+            .keys()
+            .map(String::as_str)
+            .filter(|key| *key != "type")
+            .collect();
+        let mut schema_fields: Vec<&str> = event_schema
+            .fields
+            .iter()
+            .map(|f| f.name)
+            .collect();
+
+        fields.sort();
+        schema_fields.sort();
+
+        assert_eq!(json["type"], event_schema.name);
+        assert_eq!(fields, schema_fields);
+    }
+
+    fn test_user() -> state::User {
+        state::UserBuilder::default()
+            .id("user".to_string())
+            .display_name("user".to_string())
+            .room_id("room".to_string())
+            .build()
+            .unwrap()
+    }
+
+    fn test_settings() -> state::RoomSettings {
+        state::RoomSettings {
+            max_rounds: 3,
+            max_users: 8,
+            draw_time_limit: 80,
+            pick_word_time_limit: 15,
+            amount_of_words_to_pick: 3,
+        }
+    }
+
+    #[test]
+    fn test_ids_are_unique_per_direction() {
+        for schema in [
+            client_to_server_events(),
+            server_to_client_events(),
+        ] {
+            for (index, event) in schema.iter().enumerate() {
+                assert_eq!(event.id as usize, index);
+            }
+        }
+    }
+
+    #[test]
+    fn test_client_events_match_the_schema() {
+        let schema = client_to_server_events();
+        let string = || "a".to_string();
+        let events = vec![
+            events::ClientToServerEvents::StartGame,
+            events::ClientToServerEvents::PickAWord { word: string() },
+            events::ClientToServerEvents::PointerDown,
+            events::ClientToServerEvents::PointerMove { x: 0.5, y: 0.5 },
+            events::ClientToServerEvents::PointerUp,
+            events::ClientToServerEvents::PointerLeave,
+            events::ClientToServerEvents::ChangeColor { color: string() },
+            events::ClientToServerEvents::Message { message: string() },
+            events::ClientToServerEvents::UpdateSettings {
+                settings: test_settings(),
+            },
+            events::ClientToServerEvents::UploadCustomWords {
+                words: string(),
+                mode: state::CustomWordsMode::Mix,
+            },
+            events::ClientToServerEvents::SelectWordPack {
+                category: string(),
+                language: string(),
+            },
+            events::ClientToServerEvents::StrokeBatch { points: vec![] },
+        ];
+
+        assert_eq!(events.len(), schema.len());
+
+        for event in events {
+            assert_matches_schema(&schema, u8::from(&event), event);
+        }
+    }
+
+    #[test]
+    fn test_server_events_match_the_schema() {
+        let schema = server_to_client_events();
+        let string = || "a".to_string();
+        let room = state::RoomBuilder::default()
+            .id(string())
+            .host_id(string())
+            .build()
+            .unwrap();
+        let events = vec![
+            events::ServerToClientEvents::Error {
+                code: events::ErrorCode::Internal,
+                message: None,
+            },
+            events::ServerToClientEvents::ConnectError {
+                code: events::ErrorCode::RoomFull,
+                message: Some(string()),
+            },
+            events::ServerToClientEvents::UserJoined { user: test_user() },
+            events::ServerToClientEvents::UserLeft { user_id: string() },
+            events::ServerToClientEvents::StartGame,
+            events::ServerToClientEvents::PickAWord {
+                words_to_pick: vec![],
+            },
+            events::ServerToClientEvents::EndGame,
+            events::ServerToClientEvents::ResetRoom,
+            events::ServerToClientEvents::NewTurn {
+                user_id_to_draw: string(),
+            },
+            events::ServerToClientEvents::NewWord { word: string() },
+            events::ServerToClientEvents::NewRound { round: 1 },
+            events::ServerToClientEvents::NewHost { user_id: string() },
+            events::ServerToClientEvents::PointerDown,
+            events::ServerToClientEvents::PointerMove { x: 0.5, y: 0.5 },
+            events::ServerToClientEvents::PointerUp,
+            events::ServerToClientEvents::PointerLeave,
+            events::ServerToClientEvents::ChangeColor { color: string() },
+            events::ServerToClientEvents::SendGameState {
+                room: Box::new(room),
+                user: test_user(),
+                users_in_room: vec![],
+                resume_token: string(),
+            },
+            events::ServerToClientEvents::Message {
+                user_id: string(),
+                message: string(),
+            },
+            events::ServerToClientEvents::AddScore {
+                user_id: string(),
+                score: 1,
+            },
+            events::ServerToClientEvents::Tick { time_left: 1 },
+            events::ServerToClientEvents::UserGuessed { user_id: string() },
+            events::ServerToClientEvents::SystemMessage { message: string() },
+            events::ServerToClientEvents::RevealWord { word: string() },
+            events::ServerToClientEvents::Hint { word: string() },
+            events::ServerToClientEvents::CloseGuess { guess: string() },
+            events::ServerToClientEvents::SettingsUpdated {
+                settings: test_settings(),
+            },
+            events::ServerToClientEvents::CanvasSnapshot { actions: vec![] },
+            events::ServerToClientEvents::StrokeBatch { points: vec![] },
+        ];
+
+        assert_eq!(events.len(), schema.len());
+
+        for event in events {
+            assert_matches_schema(&schema, u8::from(&event), event);
+        }
+    }
+}
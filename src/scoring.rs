@@ -0,0 +1,97 @@
+//! Scoring rules for a turn.
+//!
+//! Guessers earn more points the faster they guess and the earlier they are
+//! in the guess order. The user drawing earns points for every correct guess,
+//! also scaled by how much time was left when the guess came in.
+
+/// The most points a guesser can earn, i.e., the first to guess with the full
+/// time still left.
+pub const MAX_GUESSER_SCORE: u16 = 200;
+/// The least points a correct guess is worth, no matter how late it is.
+pub const MIN_GUESSER_SCORE: u16 = 20;
+/// Points taken away from a guesser for every user who guessed before them.
+pub const GUESS_ORDER_PENALTY: u16 = 10;
+/// The most points the user drawing can earn for a single correct guess.
+pub const MAX_DRAWER_SCORE_PER_GUESS: u16 = 50;
+/// The least points the user drawing earns for a single correct guess.
+pub const MIN_DRAWER_SCORE_PER_GUESS: u16 = 10;
+
+/// Scales `max` down to `min` linearly depending on how much of `time_limit`
+/// is still left.
+fn scale_by_time_left(min: u16, max: u16, time_left: u8, time_limit: u8) -> u16 {
+    if time_limit == 0 {
+        return min;
+    }
+
+    let time_left = time_left.min(time_limit) as u32;
+    let range = (max - min) as u32;
+
+    min + (range * time_left / time_limit as u32) as u16
+}
+
+/// Returns the points a guesser earns.
+///
+/// `guess_order` is how many users have already guessed the word in the
+/// current turn, so the first one to guess has a `guess_order` of 0.
+pub fn guesser_score(time_left: u8, time_limit: u8, guess_order: usize) -> u16 {
+    let score =
+        scale_by_time_left(MIN_GUESSER_SCORE, MAX_GUESSER_SCORE, time_left, time_limit);
+    let penalty =
+        GUESS_ORDER_PENALTY.saturating_mul(guess_order.min(u16::MAX as usize) as u16);
+
+    score
+        .saturating_sub(penalty)
+        .max(MIN_GUESSER_SCORE)
+}
+
+/// Returns the points the user drawing earns for a single correct guess.
+pub fn drawer_score(time_left: u8, time_limit: u8) -> u16 {
+    scale_by_time_left(
+        MIN_DRAWER_SCORE_PER_GUESS,
+        MAX_DRAWER_SCORE_PER_GUESS,
+        time_left,
+        time_limit,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guesser_score_with_full_time_left() {
+        assert_eq!(guesser_score(60, 60, 0), MAX_GUESSER_SCORE);
+    }
+
+    #[test]
+    fn test_guesser_score_decays_with_time() {
+        let early = guesser_score(50, 60, 0);
+        let late = guesser_score(10, 60, 0);
+
+        assert!(early > late);
+        assert_eq!(guesser_score(30, 60, 0), 110);
+    }
+
+    #[test]
+    fn test_guesser_score_decays_with_guess_order() {
+        assert_eq!(
+            guesser_score(60, 60, 1),
+            MAX_GUESSER_SCORE - GUESS_ORDER_PENALTY
+        );
+        assert!(guesser_score(60, 60, 0) > guesser_score(60, 60, 3));
+    }
+
+    #[test]
+    fn test_guesser_score_never_goes_below_minimum() {
+        assert_eq!(guesser_score(0, 60, 0), MIN_GUESSER_SCORE);
+        assert_eq!(guesser_score(0, 60, 100), MIN_GUESSER_SCORE);
+        assert_eq!(guesser_score(10, 0, 0), MIN_GUESSER_SCORE);
+    }
+
+    #[test]
+    fn test_drawer_score() {
+        assert_eq!(drawer_score(60, 60), MAX_DRAWER_SCORE_PER_GUESS);
+        assert_eq!(drawer_score(0, 60), MIN_DRAWER_SCORE_PER_GUESS);
+        assert_eq!(drawer_score(30, 60), 30);
+    }
+}
@@ -0,0 +1,103 @@
+//! Helpers to know how close a guess is to the word being drawn.
+
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Words with at most this many characters only allow one mistake for a
+/// guess to be considered close. Longer words allow two.
+pub const SHORT_WORD_LENGTH: usize = 6;
+
+/// Normalizes `text` so that guesses can be compared regardless of case,
+/// diacritics, and whitespace, e.g., " Crème  Brûlée" becomes "creme brulee".
+pub fn normalize(text: &str) -> String {
+    text.nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect::<String>()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Returns the minimum amount of single character insertions, deletions, or
+/// substitutions needed to turn `a` into `b`.
+pub fn levenshtein_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<char>>();
+    let b = b.chars().collect::<Vec<char>>();
+    let mut previous_row = (0..=b.len()).collect::<Vec<usize>>();
+    let mut current_row = vec![0; b.len() + 1];
+
+    for (i, a_char) in a.iter().enumerate() {
+        current_row[0] = i + 1;
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution_cost = if a_char == b_char { 0 } else { 1 };
+
+            current_row[j + 1] = (previous_row[j + 1] + 1)
+                .min(current_row[j] + 1)
+                .min(previous_row[j] + substitution_cost);
+        }
+
+        std::mem::swap(&mut previous_row, &mut current_row);
+    }
+
+    previous_row[b.len()]
+}
+
+/// Returns the maximum distance a guess can have from `word` to be considered
+/// close.
+pub fn max_close_guess_distance(word: &str) -> usize {
+    if word.chars().count() <= SHORT_WORD_LENGTH {
+        1
+    } else {
+        2
+    }
+}
+
+/// Returns true if `guess` is not the `word` itself but only a few mistakes
+/// away from it.
+pub fn is_close_guess(word: &str, guess: &str) -> bool {
+    let distance = levenshtein_distance(word, guess);
+
+    distance != 0 && distance <= max_close_guess_distance(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("apple"), "apple");
+        assert_eq!(normalize("Apple "), "apple");
+        assert_eq!(normalize("APPLE"), "apple");
+        assert_eq!(normalize("  ice \t  cream "), "ice cream");
+        assert_eq!(normalize("Crème Brûlée"), "creme brulee");
+        assert_eq!(normalize("ＡＰＰＬＥ"), "apple");
+    }
+
+    #[test]
+    fn test_levenshtein_distance() {
+        assert_eq!(levenshtein_distance("", ""), 0);
+        assert_eq!(levenshtein_distance("apple", "apple"), 0);
+        assert_eq!(levenshtein_distance("apple", "aple"), 1);
+        assert_eq!(levenshtein_distance("apple", "appple"), 1);
+        assert_eq!(levenshtein_distance("apple", "apble"), 1);
+        assert_eq!(levenshtein_distance("kitten", "sitting"), 3);
+        assert_eq!(levenshtein_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_levenshtein_distance_counts_characters_not_bytes() {
+        assert_eq!(levenshtein_distance("café", "cafe"), 1);
+    }
+
+    #[test]
+    fn test_is_close_guess() {
+        assert!(is_close_guess("apple", "aple"));
+        assert!(!is_close_guess("apple", "apple"));
+        assert!(!is_close_guess("apple", "ale"));
+        assert!(is_close_guess("elephant", "elefant"));
+        assert!(is_close_guess("elephant", "elephnat"));
+        assert!(!is_close_guess("elephant", "giraffe"));
+    }
+}
@@ -1,4 +1,6 @@
-use crate::utils;
+use rand::seq::SliceRandom;
+
+use crate::{clock, room, similarity, utils, word_packs};
 
 #[derive(
     rocket::serde::Serialize,
@@ -17,28 +19,151 @@ pub enum Visibility {
     Private,
 }
 
-#[derive(Clone, Debug)]
-pub struct WordToDraw(pub String);
+/// How the custom words uploaded by the host of a room are used.
+#[derive(
+    rocket::serde::Serialize,
+    rocket::serde::Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Debug,
+    Default,
+)]
+pub enum CustomWordsMode {
+    /// Custom words are picked along with the default words.
+    #[default]
+    #[serde(rename = "mix")]
+    Mix,
+    /// Only the custom words are picked.
+    #[serde(rename = "replace")]
+    Replace,
+}
 
-impl WordToDraw {
-    pub fn get_three_words() -> [String; 3] {
-        let mut words: Vec<&str> = Vec::with_capacity(3);
+impl TryFrom<u8> for CustomWordsMode {
+    type Error = Box<dyn std::error::Error>;
 
-        while words.len() != 3 {
-            let word = utils::get_random_word();
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Mix),
+            1 => Ok(Self::Replace),
+            _ => Err("Invalid custom words mode".into()),
+        }
+    }
+}
+
+impl From<CustomWordsMode> for u8 {
+    fn from(value: CustomWordsMode) -> Self {
+        match value {
+            CustomWordsMode::Mix => 0,
+            CustomWordsMode::Replace => 1,
+        }
+    }
+}
+
+/// Where the words of a room come from.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct WordSource {
+    /// The word pack picked by the host of the room. The words embedded in the
+    /// binary are used if this is `None`.
+    pub pack: Option<std::sync::Arc<word_packs::WordPack>>,
+    pub custom_words: Vec<String>,
+    pub custom_words_mode: CustomWordsMode,
+}
+
+impl WordSource {
+    /// Parses a comma or newline separated list of words uploaded by the host of a
+    /// room, making sure it is within the limits set in `utils::consts`.
+    pub fn parse_custom_words(
+        input: &str,
+        mode: CustomWordsMode,
+    ) -> Result<Vec<String>, String> {
+        let mut words: Vec<String> = Vec::new();
 
-            if words.contains(&word) {
+        for word in input.split([',', '\n']) {
+            let word = word
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ");
+
+            if word.is_empty() {
+                continue;
+            }
+
+            if word.chars().count() > utils::consts::MAX_CUSTOM_WORD_LENGTH {
+                return Err(format!(
+                    "Words cannot be longer than {} characters",
+                    utils::consts::MAX_CUSTOM_WORD_LENGTH
+                ));
+            }
+
+            let normalized_word = similarity::normalize(&word);
+
+            if words
+                .iter()
+                .any(|w| similarity::normalize(w) == normalized_word)
+            {
                 continue;
             }
 
             words.push(word);
+
+            if words.len() > utils::consts::MAX_CUSTOM_WORDS {
+                return Err(format!(
+                    "Cannot have more than {} custom words",
+                    utils::consts::MAX_CUSTOM_WORDS
+                ));
+            }
+        }
+
+        if mode == CustomWordsMode::Replace
+            && words.len() < utils::consts::MIN_CUSTOM_WORDS_TO_REPLACE
+        {
+            return Err(format!(
+                "Need at least {} custom words to replace the default words",
+                utils::consts::MIN_CUSTOM_WORDS_TO_REPLACE
+            ));
+        }
+
+        Ok(words)
+    }
+
+    /// Returns every word that can be picked from this source.
+    fn words(&self) -> Vec<&str> {
+        let mut words = self
+            .custom_words
+            .iter()
+            .map(|word| word.as_str())
+            .collect::<Vec<&str>>();
+
+        if self.custom_words_mode == CustomWordsMode::Mix || words.is_empty() {
+            match &self.pack {
+                Some(pack) => words.extend(pack.words.iter().map(|word| word.as_str())),
+                None => words.extend(utils::consts::WORDS.iter()),
+            }
         }
 
-        [
-            words.get(0).unwrap().to_string(),
-            words.get(1).unwrap().to_string(),
-            words.get(2).unwrap().to_string(),
-        ]
+        words.sort_unstable();
+        words.dedup();
+
+        words
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WordToDraw(pub String);
+
+impl WordToDraw {
+    /// Returns `amount` distinct random words from `word_source` for a user to pick
+    /// from, or less if the source does not have enough words.
+    pub fn get_words_to_pick(word_source: &WordSource, amount: usize) -> Vec<String> {
+        word_source
+            .words()
+            .choose_multiple(&mut rand::thread_rng(), amount)
+            .map(|word| word.to_string())
+            .collect()
     }
 }
 
@@ -49,12 +174,333 @@ mod test {
     #[test]
     fn test_words_to_draw_not_repeated() {
         for _ in 0..(utils::consts::WORDS.len() / 3) {
-            let words = WordToDraw::get_three_words();
+            let words = WordToDraw::get_words_to_pick(&WordSource::default(), 3);
+            assert_eq!(words.len(), 3);
             assert_ne!(words[0], words[1]);
             assert_ne!(words[0], words[2]);
             assert_ne!(words[1], words[2]);
         }
     }
+
+    #[test]
+    fn test_words_to_draw_from_custom_words() {
+        let custom_words = ["red", "green", "blue", "cyan", "magenta"]
+            .map(|word| word.to_string())
+            .to_vec();
+        let word_source = WordSource {
+            pack: None,
+            custom_words: custom_words.clone(),
+            custom_words_mode: CustomWordsMode::Replace,
+        };
+
+        for _ in 0..20 {
+            let words = WordToDraw::get_words_to_pick(&word_source, 3);
+            assert_eq!(words.len(), 3);
+            assert!(words
+                .iter()
+                .all(|word| custom_words.contains(word)));
+        }
+
+        let words = WordToDraw::get_words_to_pick(&word_source, 10);
+        assert_eq!(words.len(), custom_words.len());
+
+        let word_source = WordSource {
+            custom_words_mode: CustomWordsMode::Mix,
+            ..word_source
+        };
+
+        assert_eq!(
+            word_source.words().len(),
+            utils::consts::WORDS.len() + custom_words.len()
+        );
+    }
+
+    #[test]
+    fn test_words_to_draw_from_word_pack() {
+        let pack_words = ["gato", "perro", "pez", "vaca", "burro"]
+            .map(|word| word.to_string())
+            .to_vec();
+        let word_source = WordSource {
+            pack: Some(std::sync::Arc::new(word_packs::WordPack {
+                category: "animals".to_string(),
+                language: "es".to_string(),
+                words: pack_words.clone(),
+            })),
+            ..Default::default()
+        };
+
+        for _ in 0..20 {
+            let words = WordToDraw::get_words_to_pick(&word_source, 3);
+            assert!(words.iter().all(|word| pack_words.contains(word)));
+        }
+    }
+
+    #[test]
+    fn test_parse_custom_words() {
+        assert_eq!(
+            WordSource::parse_custom_words(
+                " apple, ice   cream\n\nBanana,APPLE,",
+                CustomWordsMode::Mix
+            )
+            .unwrap(),
+            vec!["apple", "ice cream", "Banana"]
+        );
+        assert!(WordSource::parse_custom_words("a,b", CustomWordsMode::Replace).is_err());
+        assert!(WordSource::parse_custom_words(
+            &"a".repeat(utils::consts::MAX_CUSTOM_WORD_LENGTH + 1),
+            CustomWordsMode::Mix
+        )
+        .is_err());
+        assert!(WordSource::parse_custom_words(
+            &(0..=utils::consts::MAX_CUSTOM_WORDS)
+                .map(|i| i.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+            CustomWordsMode::Mix
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_room_settings_validation() {
+        let room = RoomBuilder::default()
+            .id("room".to_string())
+            .host_id("host".to_string())
+            .amount_of_users(4)
+            .build()
+            .unwrap();
+        let settings = room.settings();
+
+        assert!(settings.validate(room.amount_of_users).is_ok());
+        assert!(RoomSettings {
+            max_rounds: 0,
+            ..settings.clone()
+        }
+        .validate(room.amount_of_users)
+        .is_err());
+        assert!(RoomSettings {
+            max_users: 3,
+            ..settings.clone()
+        }
+        .validate(room.amount_of_users)
+        .is_err());
+        assert!(RoomSettings {
+            draw_time_limit: utils::consts::MAX_DRAW_TIME_LIMIT + 1,
+            ..settings.clone()
+        }
+        .validate(room.amount_of_users)
+        .is_err());
+        assert!(RoomSettings {
+            pick_word_time_limit: utils::consts::MIN_PICK_WORD_TIME_LIMIT - 1,
+            ..settings.clone()
+        }
+        .validate(room.amount_of_users)
+        .is_err());
+        assert!(RoomSettings {
+            amount_of_words_to_pick: 0,
+            ..settings
+        }
+        .validate(room.amount_of_users)
+        .is_err());
+    }
+
+    #[test]
+    fn test_room_redacted_for_user() {
+        let drawer = UserBuilder::default()
+            .id("drawer".to_string())
+            .display_name("drawer".to_string())
+            .room_id("room".to_string())
+            .build()
+            .unwrap();
+        let mut guesser = UserBuilder::default()
+            .id("guesser".to_string())
+            .display_name("guesser".to_string())
+            .room_id("room".to_string())
+            .build()
+            .unwrap();
+        let mut room = RoomBuilder::default()
+            .id("room".to_string())
+            .host_id(drawer.id.clone())
+            .state(RoomState::Playing {
+                playing_state: PlayingState::Drawing {
+                    current_word: "apple".to_string(),
+                    time_left: 10,
+                    revealed_indices: vec![0],
+                },
+                current_user_id: drawer.id.clone(),
+                current_round: 1,
+            })
+            .build()
+            .unwrap();
+        let current_word = |room: Room| match room.state {
+            RoomState::Playing {
+                playing_state: PlayingState::Drawing { current_word, .. },
+                ..
+            } => current_word,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(current_word(room.redacted_for(&drawer)), "apple");
+        assert_eq!(current_word(room.redacted_for(&guesser)), "a****");
+
+        guesser.has_guessed = true;
+
+        assert_eq!(current_word(room.redacted_for(&guesser)), "apple");
+
+        room.state = RoomState::Playing {
+            playing_state: PlayingState::PickingAWord {
+                words_to_pick: vec!["apple".to_string(), "pear".to_string()],
+                time_left: 10,
+            },
+            current_user_id: drawer.id.clone(),
+            current_round: 1,
+        };
+
+        let words_to_pick = |room: Room| match room.state {
+            RoomState::Playing {
+                playing_state: PlayingState::PickingAWord { words_to_pick, .. },
+                ..
+            } => words_to_pick,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(
+            words_to_pick(room.redacted_for(&drawer)),
+            vec!["apple", "pear"]
+        );
+        assert_eq!(words_to_pick(room.redacted_for(&guesser)), vec!["", ""]);
+    }
+
+    #[test]
+    fn test_record_canvas_action() {
+        let mut room = RoomBuilder::default()
+            .id("room".to_string())
+            .host_id("drawer".to_string())
+            .build()
+            .unwrap();
+
+        assert!(!room.record_canvas_action("drawer", CanvasAction::PointerDown));
+
+        room.state = RoomState::Playing {
+            playing_state: PlayingState::Drawing {
+                current_word: "apple".to_string(),
+                time_left: 10,
+                revealed_indices: vec![],
+            },
+            current_user_id: "drawer".to_string(),
+            current_round: 1,
+        };
+
+        assert!(room.record_canvas_action("drawer", CanvasAction::PointerDown));
+        assert!(room.record_canvas_action(
+            "drawer",
+            CanvasAction::PointerMove { x: 1.0, y: 2.0 }
+        ));
+        assert!(!room.record_canvas_action("guesser", CanvasAction::PointerUp));
+        assert_eq!(
+            room.canvas,
+            vec![
+                CanvasAction::PointerDown,
+                CanvasAction::PointerMove { x: 1.0, y: 2.0 }
+            ]
+        );
+
+        // The history is capped by the points it holds, however they are batched.
+        let points =
+            vec![StrokePoint { x: 0, y: 0 }; utils::consts::MAX_CANVAS_POINTS / 2];
+
+        assert!(room.record_canvas_action(
+            "drawer",
+            CanvasAction::StrokeBatch {
+                points: points.clone()
+            }
+        ));
+        assert!(!room.record_canvas_action(
+            "drawer",
+            CanvasAction::StrokeBatch {
+                points: points.clone()
+            }
+        ));
+        assert!(room.record_canvas_action(
+            "drawer",
+            CanvasAction::StrokeBatch {
+                points: points[2..].to_vec()
+            }
+        ));
+        assert_eq!(room.canvas_points, utils::consts::MAX_CANVAS_POINTS);
+
+        room.clear_canvas();
+
+        assert!(room.canvas.is_empty());
+        assert_eq!(room.canvas_points, 0);
+    }
+
+    #[test]
+    fn test_apply_room_settings() {
+        let mut room = RoomBuilder::default()
+            .id("room".to_string())
+            .host_id("host".to_string())
+            .build()
+            .unwrap();
+        let settings = RoomSettings {
+            max_rounds: 5,
+            max_users: 8,
+            draw_time_limit: 90,
+            pick_word_time_limit: 15,
+            amount_of_words_to_pick: 4,
+        };
+
+        room.apply_settings(&settings);
+
+        assert_eq!(room.settings(), settings);
+    }
+}
+
+/// Something the user drawing did on the canvas. The actions of the current turn
+/// are kept so users who join mid-turn can replay them.
+#[derive(
+    rocket::serde::Serialize, rocket::serde::Deserialize, Clone, PartialEq, Debug,
+)]
+#[serde(tag = "type")]
+pub enum CanvasAction {
+    #[serde(rename = "pointerDown")]
+    PointerDown,
+    #[serde(rename = "pointerMove")]
+    PointerMove { x: f64, y: f64 },
+    #[serde(rename = "pointerUp")]
+    PointerUp,
+    #[serde(rename = "pointerLeave")]
+    PointerLeave,
+    #[serde(rename = "changeColor")]
+    ChangeColor { color: String },
+    #[serde(rename = "strokeBatch")]
+    StrokeBatch { points: Vec<StrokePoint> },
+}
+
+impl CanvasAction {
+    /// How much of `utils::consts::MAX_CANVAS_POINTS` keeping the action takes up.
+    pub fn points(&self) -> usize {
+        match self {
+            CanvasAction::StrokeBatch { points } => points.len(),
+            _ => 1,
+        }
+    }
+}
+
+/// A point of a stroke with its coordinates normalized to the canvas, i.e., 0 is
+/// the left or top edge and `u16::MAX` is the right or bottom edge.
+#[derive(
+    rocket::serde::Serialize,
+    rocket::serde::Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Debug,
+)]
+pub struct StrokePoint {
+    pub x: u16,
+    pub y: u16,
 }
 
 #[derive(
@@ -71,23 +517,30 @@ pub enum PlayingState {
     #[serde(rename = "pickingAWord")]
     PickingAWord {
         #[serde(rename = "wordsToPick")]
-        words_to_pick: [String; 3],
-        #[serde(skip_serializing)]
+        words_to_pick: Vec<String>,
+        #[serde(skip_serializing, default)]
         time_left: u8,
     },
     #[serde(rename = "drawing")]
     Drawing {
         #[serde(rename = "currentWord")]
         current_word: String,
-        #[serde(skip_serializing)]
+        #[serde(skip_serializing, default)]
         time_left: u8,
+        /// The indices of the characters in `current_word` that have been
+        /// revealed as hints.
+        #[serde(skip_serializing, default)]
+        revealed_indices: Vec<usize>,
     },
 }
 
 impl Default for PlayingState {
     fn default() -> Self {
         Self::PickingAWord {
-            words_to_pick: WordToDraw::get_three_words(),
+            words_to_pick: WordToDraw::get_words_to_pick(
+                &WordSource::default(),
+                utils::consts::AMOUNT_OF_WORDS_TO_PICK as usize,
+            ),
             time_left: utils::consts::PICK_WORD_TIME_LIMIT,
         }
     }
@@ -124,6 +577,7 @@ pub enum RoomState {
     rocket::serde::Deserialize,
     derive_builder::Builder,
     Clone,
+    PartialEq,
     Debug,
 )]
 pub struct Room {
@@ -140,42 +594,475 @@ pub struct Room {
     #[builder(default = "3")]
     #[serde(rename = "maxRounds")]
     pub max_rounds: u8,
+    /// The amount of time a user has to draw a word in seconds.
+    #[builder(default = "utils::consts::DRAW_IME_LIMIT")]
+    #[serde(rename = "drawTimeLimit")]
+    pub draw_time_limit: u8,
+    /// The amount of time a user has to pick a word in seconds.
+    #[builder(default = "utils::consts::PICK_WORD_TIME_LIMIT")]
+    #[serde(rename = "pickWordTimeLimit")]
+    pub pick_word_time_limit: u8,
+    #[builder(default = "utils::consts::AMOUNT_OF_WORDS_TO_PICK")]
+    #[serde(rename = "amountOfWordsToPick")]
+    pub amount_of_words_to_pick: u8,
     #[builder(default = "1")]
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing, default)]
     pub amount_of_users: u8,
+    #[builder(default)]
+    #[serde(skip)]
+    pub word_source: WordSource,
+    /// What has been drawn in the current turn.
+    #[builder(default)]
+    #[serde(skip)]
+    pub canvas: Vec<CanvasAction>,
+    /// The sum of `CanvasAction::points` over `canvas`.
+    #[builder(default)]
+    #[serde(skip)]
+    pub canvas_points: usize,
 }
 
-#[derive(rocket::serde::Serialize, derive_builder::Builder, Clone, Debug)]
+impl Room {
+    pub fn settings(&self) -> RoomSettings {
+        RoomSettings {
+            max_rounds: self.max_rounds,
+            max_users: self.max_users,
+            draw_time_limit: self.draw_time_limit,
+            pick_word_time_limit: self.pick_word_time_limit,
+            amount_of_words_to_pick: self.amount_of_words_to_pick,
+        }
+    }
+
+    /// Returns a copy of the room that is safe to send to `user`, i.e., without the
+    /// word being drawn or the words to pick unless `user` is supposed to know them.
+    pub fn redacted_for(&self, user: &User) -> Room {
+        let mut room = self.clone();
+
+        if let RoomState::Playing {
+            playing_state,
+            current_user_id,
+            ..
+        } = &mut room.state
+        {
+            let is_drawing = *current_user_id == user.id;
+
+            match playing_state {
+                PlayingState::PickingAWord { words_to_pick, .. } => {
+                    if !is_drawing {
+                        words_to_pick
+                            .iter_mut()
+                            .for_each(|word| word.clear());
+                    }
+                }
+                PlayingState::Drawing {
+                    current_word,
+                    revealed_indices,
+                    ..
+                } => {
+                    if !is_drawing && !user.has_guessed {
+                        *current_word = utils::obfuscate_word_with_hints(
+                            current_word,
+                            revealed_indices,
+                        );
+                    }
+                }
+            }
+        }
+
+        room
+    }
+
+    /// Keeps `action` in the canvas history if it was done by the user drawing
+    /// while a word is being drawn. Returns true if it was kept.
+    pub fn record_canvas_action(&mut self, user_id: &str, action: CanvasAction) -> bool {
+        let RoomState::Playing {
+            playing_state: PlayingState::Drawing { .. },
+            current_user_id,
+            ..
+        } = &self.state
+        else {
+            return false;
+        };
+
+        if current_user_id != user_id
+            || self.canvas_points + action.points() > utils::consts::MAX_CANVAS_POINTS
+        {
+            return false;
+        }
+
+        self.canvas_points += action.points();
+        self.canvas.push(action);
+
+        true
+    }
+
+    pub fn clear_canvas(&mut self) {
+        self.canvas.clear();
+        self.canvas_points = 0;
+    }
+
+    pub fn apply_settings(&mut self, settings: &RoomSettings) {
+        self.max_rounds = settings.max_rounds;
+        self.max_users = settings.max_users;
+        self.draw_time_limit = settings.draw_time_limit;
+        self.pick_word_time_limit = settings.pick_word_time_limit;
+        self.amount_of_words_to_pick = settings.amount_of_words_to_pick;
+    }
+}
+
+/// The parts of a `Room` that its host can change while waiting for the game to
+/// start.
+#[derive(
+    rocket::serde::Serialize, rocket::serde::Deserialize, Clone, PartialEq, Eq, Debug,
+)]
+pub struct RoomSettings {
+    #[serde(rename = "maxRounds")]
+    pub max_rounds: u8,
+    #[serde(rename = "maxUsers")]
+    pub max_users: u8,
+    #[serde(rename = "drawTimeLimit")]
+    pub draw_time_limit: u8,
+    #[serde(rename = "pickWordTimeLimit")]
+    pub pick_word_time_limit: u8,
+    #[serde(rename = "amountOfWordsToPick")]
+    pub amount_of_words_to_pick: u8,
+}
+
+impl RoomSettings {
+    /// Checks if the settings are within the allowed limits for a room that
+    /// currently has `amount_of_users` users.
+    pub fn validate(&self, amount_of_users: u8) -> Result<(), String> {
+        use utils::consts;
+
+        if !(consts::MIN_ROUNDS..=consts::MAX_ROUNDS).contains(&self.max_rounds) {
+            return Err(format!(
+                "Rounds must be between {} and {}",
+                consts::MIN_ROUNDS,
+                consts::MAX_ROUNDS
+            ));
+        }
+
+        if !(consts::MIN_USERS..=consts::MAX_USERS).contains(&self.max_users) {
+            return Err(format!(
+                "Max players must be between {} and {}",
+                consts::MIN_USERS,
+                consts::MAX_USERS
+            ));
+        }
+
+        if self.max_users < amount_of_users {
+            return Err(
+                "Max players cannot be less than the players in the room".to_string()
+            );
+        }
+
+        if !(consts::MIN_DRAW_TIME_LIMIT..=consts::MAX_DRAW_TIME_LIMIT)
+            .contains(&self.draw_time_limit)
+        {
+            return Err(format!(
+                "Draw time must be between {} and {} seconds",
+                consts::MIN_DRAW_TIME_LIMIT,
+                consts::MAX_DRAW_TIME_LIMIT
+            ));
+        }
+
+        if !(consts::MIN_PICK_WORD_TIME_LIMIT..=consts::MAX_PICK_WORD_TIME_LIMIT)
+            .contains(&self.pick_word_time_limit)
+        {
+            return Err(format!(
+                "Pick time must be between {} and {} seconds",
+                consts::MIN_PICK_WORD_TIME_LIMIT,
+                consts::MAX_PICK_WORD_TIME_LIMIT
+            ));
+        }
+
+        if !(consts::MIN_AMOUNT_OF_WORDS_TO_PICK..=consts::MAX_AMOUNT_OF_WORDS_TO_PICK)
+            .contains(&self.amount_of_words_to_pick)
+        {
+            return Err(format!(
+                "Word choices must be between {} and {}",
+                consts::MIN_AMOUNT_OF_WORDS_TO_PICK,
+                consts::MAX_AMOUNT_OF_WORDS_TO_PICK
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(
+    rocket::serde::Serialize,
+    rocket::serde::Deserialize,
+    derive_builder::Builder,
+    Clone,
+    PartialEq,
+    Debug,
+)]
 pub struct User {
     pub id: String,
     #[serde(rename = "displayName")]
     pub display_name: String,
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing, default)]
     pub room_id: String,
     #[builder(default = "false")]
-    #[serde(skip_serializing)]
+    #[serde(rename = "hasDrawn")]
     pub has_drawn: bool,
     #[builder(default = "false")]
-    #[serde(skip_serializing)]
+    #[serde(rename = "hasGuessed")]
     pub has_guessed: bool,
     #[builder(default)]
-    #[serde(skip_serializing)]
     pub score: u16,
+    /// Lets the user take back their place in the room if they lose connection.
+    /// Only ever sent to the user it belongs to.
+    #[builder(default = "utils::gen_resume_token()")]
+    #[serde(skip_serializing, default)]
+    pub resume_token: String,
+    /// Changes every time the user connects, so we can tell if the user has
+    /// reconnected since they last lost connection.
+    #[builder(default = "utils::gen_random_id()")]
+    #[serde(skip_serializing, default)]
+    pub connection_id: String,
+    #[builder(default = "true")]
+    #[serde(skip_serializing, default)]
+    pub is_connected: bool,
+    /// Spectators only watch a room, so they neither draw nor guess.
+    #[builder(default = "false")]
+    #[serde(rename = "isSpectator")]
+    pub is_spectator: bool,
 }
 
-#[derive(Clone)]
-pub enum TickerCommand {
-    Delete,
+impl User {
+    /// Returns true if the user is a player, i.e., not a spectator, in the room
+    /// with the id `room_id`.
+    pub fn is_playing_in(&self, room_id: &str) -> bool {
+        self.room_id == room_id && !self.is_spectator
+    }
 }
 
-#[derive(Clone, derive_builder::Builder)]
-pub struct TickerMsg {
-    pub room_id: String,
-    pub command: TickerCommand,
+/// The task of every room keyed by the id of the room. Rooms are run by their
+/// own tasks, so this is only locked to find a room or to keep track of who can
+/// resume their session in it.
+///
+/// The tasks of rooms never lock this themselves, so they are never held up by
+/// each other. They send a `RoomsUpdate` instead, which is applied by the task
+/// started along with `GameState`.
+#[derive(Debug, Default)]
+pub struct Rooms {
+    rooms: std::collections::HashMap<String, room::RoomHandle>,
+    /// The id of the room of every user, keyed by their resume token.
+    room_ids_by_resume_token: std::collections::HashMap<String, String>,
+}
+
+impl Rooms {
+    pub fn len(&self) -> usize {
+        self.rooms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rooms.is_empty()
+    }
+
+    pub fn get(&self, room_id: &str) -> Option<&room::RoomHandle> {
+        self.rooms.get(room_id)
+    }
+
+    pub fn insert(&mut self, room_id: String, room: room::RoomHandle) {
+        self.rooms.insert(room_id, room);
+    }
+
+    pub fn remove(&mut self, room_id: &str) -> Option<room::RoomHandle> {
+        self.rooms.remove(room_id)
+    }
+
+    pub fn add_resume_token(&mut self, resume_token: String, room_id: String) {
+        self.room_ids_by_resume_token
+            .insert(resume_token, room_id);
+    }
+
+    pub fn remove_resume_token(&mut self, resume_token: &str) {
+        self.room_ids_by_resume_token.remove(resume_token);
+    }
+
+    /// Returns the room of the user with `resume_token`.
+    pub fn find_by_resume_token(&self, resume_token: &str) -> Option<&room::RoomHandle> {
+        let room_id = self.room_ids_by_resume_token.get(resume_token)?;
+
+        self.rooms.get(room_id)
+    }
+
+    pub fn update(&mut self, update: RoomsUpdate) {
+        match update {
+            RoomsUpdate::AddResumeToken {
+                resume_token,
+                room_id,
+            } => self.add_resume_token(resume_token, room_id),
+            RoomsUpdate::RemoveResumeToken { resume_token } => {
+                self.remove_resume_token(&resume_token)
+            }
+            RoomsUpdate::Remove {
+                room_id,
+                resume_tokens,
+            } => {
+                for resume_token in &resume_tokens {
+                    self.remove_resume_token(resume_token);
+                }
+
+                self.remove(&room_id);
+            }
+        }
+    }
+
+    /// Returns every room a player can join through matchmaking along with its
+    /// id.
+    pub fn available(&self) -> Vec<(String, room::RoomHandle)> {
+        self.rooms
+            .iter()
+            .filter(|(_, room)| room.is_available())
+            .map(|(room_id, room)| (room_id.clone(), room.clone()))
+            .collect()
+    }
+}
+
+/// A change to `Rooms` sent by the task of a room.
+#[derive(Debug)]
+pub enum RoomsUpdate {
+    AddResumeToken {
+        resume_token: String,
+        room_id: String,
+    },
+    RemoveResumeToken {
+        resume_token: String,
+    },
+    /// The room was closed. Whoever was still in it can no longer resume their
+    /// session.
+    Remove {
+        room_id: String,
+        resume_tokens: Vec<String>,
+    },
+}
+
+#[cfg(test)]
+mod rooms_test {
+    use super::*;
+
+    fn test_room_handle(is_available: bool) -> room::RoomHandle {
+        room::RoomHandle {
+            commands: rocket::tokio::sync::mpsc::channel(1).0,
+            is_available: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(
+                is_available,
+            )),
+            messages: rocket::tokio::sync::broadcast::channel(1).0,
+        }
+    }
+
+    #[test]
+    fn test_find_by_resume_token() {
+        let mut rooms = Rooms::default();
+
+        rooms.insert("a".to_string(), test_room_handle(true));
+        rooms.add_resume_token("token".to_string(), "a".to_string());
+
+        assert!(rooms.find_by_resume_token("token").is_some());
+
+        rooms.remove_resume_token("token");
+
+        assert!(rooms.find_by_resume_token("token").is_none());
+
+        rooms.insert("b".to_string(), test_room_handle(true));
+        rooms.add_resume_token("token".to_string(), "b".to_string());
+        rooms.remove("b");
+
+        assert!(rooms.find_by_resume_token("token").is_none());
+        assert_eq!(rooms.len(), 1);
+    }
+
+    #[test]
+    fn test_removing_a_room_removes_its_resume_tokens() {
+        let mut rooms = Rooms::default();
+
+        rooms.insert("a".to_string(), test_room_handle(true));
+        rooms.update(RoomsUpdate::AddResumeToken {
+            resume_token: "token".to_string(),
+            room_id: "a".to_string(),
+        });
+
+        assert!(rooms.find_by_resume_token("token").is_some());
+
+        rooms.update(RoomsUpdate::Remove {
+            room_id: "a".to_string(),
+            resume_tokens: vec!["token".to_string()],
+        });
+
+        assert!(rooms.is_empty());
+        assert!(rooms.room_ids_by_resume_token.is_empty());
+    }
+
+    #[test]
+    fn test_find_available_room() {
+        let mut rooms = Rooms::default();
+
+        rooms.insert("full".to_string(), test_room_handle(false));
+
+        assert!(rooms.available().is_empty());
+
+        rooms.insert("waiting".to_string(), test_room_handle(true));
+
+        assert_eq!(
+            rooms
+                .available()
+                .iter()
+                .map(|(room_id, _)| room_id.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["waiting"]
+        );
+    }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct GameState {
-    pub rooms: std::sync::Arc<rocket::futures::lock::Mutex<Vec<Room>>>,
-    pub users: std::sync::Arc<rocket::futures::lock::Mutex<Vec<User>>>,
+    pub rooms: std::sync::Arc<rocket::futures::lock::Mutex<Rooms>>,
+    /// Applied to `rooms` in order by a task of their own.
+    pub rooms_updates: rocket::tokio::sync::mpsc::UnboundedSender<RoomsUpdate>,
+    /// What the timers of every room run on.
+    pub clock: std::sync::Arc<dyn clock::Clock>,
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        Self::with_clock(std::sync::Arc::new(clock::TokioClock::default()))
+    }
+}
+
+impl GameState {
+    /// Must be called from within a Tokio runtime, which runs the task that
+    /// applies `rooms_updates`.
+    pub fn with_clock(clock: std::sync::Arc<dyn clock::Clock>) -> Self {
+        let rooms: std::sync::Arc<rocket::futures::lock::Mutex<Rooms>> =
+            Default::default();
+        let (rooms_updates, mut rooms_updates_rx) =
+            rocket::tokio::sync::mpsc::unbounded_channel();
+
+        {
+            let rooms = rooms.clone();
+
+            rocket::tokio::spawn(async move {
+                while let Some(update) = rooms_updates_rx.recv().await {
+                    let mut rooms = rooms.lock().await;
+
+                    rooms.update(update);
+
+                    // Whatever else was sent in the meantime is applied under the
+                    // same lock.
+                    while let Ok(update) = rooms_updates_rx.try_recv() {
+                        rooms.update(update);
+                    }
+                }
+            });
+        }
+
+        Self {
+            rooms,
+            rooms_updates,
+            clock,
+        }
+    }
 }
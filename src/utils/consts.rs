@@ -1,8 +1,65 @@
+/// The version of the binary protocol used with clients that do not state which
+/// versions they support.
 pub const BINARY_PROTOCOL_VERSION: u8 = 1;
+/// The oldest version of the binary protocol the server supports.
+pub const MIN_BINARY_PROTOCOL_VERSION: u8 = 1;
+/// The newest version of the binary protocol the server supports.
+pub const LATEST_BINARY_PROTOCOL_VERSION: u8 = 3;
+/// The most bytes a single field of a binary event can have in version 1, since
+/// its length is at most 255 length bytes of at most 255 each.
+pub const MAX_FIELD_LENGTH: usize = 255 * 255;
+/// The most bytes a single field of a binary event can have in any version.
+pub const MAX_PAYLOAD_SIZE: usize = 1024 * 1024;
+/// Where word packs are loaded from if `word_packs_dir` is not configured.
+pub const DEFAULT_WORD_PACKS_DIR: &str = "word-packs";
 /// The amount of time a user has to draw a word in seconds.
 pub const DRAW_IME_LIMIT: u8 = 60;
 /// The amount of time a user has to pick a word in seconds.
 pub const PICK_WORD_TIME_LIMIT: u8 = 10;
+/// How often the time left in a turn is counted down in milliseconds.
+pub const TICK_INTERVAL: u64 = 1_000;
+/// How long a user who lost connection keeps their place in a room in seconds.
+pub const RECONNECT_GRACE_PERIOD: u64 = 30;
+/// How many commands can wait for the task of a room before senders have to wait
+/// too.
+pub const ROOM_COMMANDS_CAPACITY: usize = 256;
+/// How many events sent to a room are kept for connections that have yet to
+/// receive them. A connection that falls further behind skips what it missed.
+pub const ROOM_MESSAGES_CAPACITY: usize = 1024;
+/// The most points kept in the canvas history of a single turn, where an action
+/// other than a `StrokeBatch` counts as a single point. Anything drawn after this
+/// is rejected.
+pub const MAX_CANVAS_POINTS: usize = 200_000;
+/// How often the points of a stroke sent by the user drawing are relayed to the
+/// room in milliseconds.
+pub const STROKE_BATCH_INTERVAL: u64 = 50;
+/// The most stroke points of a user relayed to the room every
+/// `STROKE_BATCH_INTERVAL`. Any more than this are dropped.
+pub const MAX_STROKE_BATCH_POINTS: usize = 1024;
+/// The amount of words a user can pick from to draw.
+pub const AMOUNT_OF_WORDS_TO_PICK: u8 = 3;
+
+pub const MIN_ROUNDS: u8 = 1;
+pub const MAX_ROUNDS: u8 = 10;
+pub const MIN_USERS: u8 = 2;
+pub const MAX_USERS: u8 = 12;
+pub const MIN_DRAW_TIME_LIMIT: u8 = 15;
+pub const MAX_DRAW_TIME_LIMIT: u8 = 240;
+pub const MIN_PICK_WORD_TIME_LIMIT: u8 = 5;
+pub const MAX_PICK_WORD_TIME_LIMIT: u8 = 30;
+pub const MIN_AMOUNT_OF_WORDS_TO_PICK: u8 = 1;
+pub const MAX_AMOUNT_OF_WORDS_TO_PICK: u8 = 5;
+
+/// The most custom words the host of a room can upload.
+pub const MAX_CUSTOM_WORDS: usize = 500;
+/// The most characters a custom word can have.
+pub const MAX_CUSTOM_WORD_LENGTH: usize = 32;
+/// The least custom words needed to replace the default words.
+pub const MIN_CUSTOM_WORDS_TO_REPLACE: usize = MAX_AMOUNT_OF_WORDS_TO_PICK as usize;
+
+/// The percentages of the drawing time left at which a letter of the word
+/// being drawn is revealed.
+pub const HINT_PERCENTAGES_OF_TIME_LEFT: [u8; 2] = [50, 25];
 
 lazy_static::lazy_static! {
     pub static ref WORDS: Vec<&'static str> = get_words();
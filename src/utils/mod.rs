@@ -1,4 +1,4 @@
-use rand::Rng;
+use rand::{seq::SliceRandom, Rng};
 
 use crate::state;
 
@@ -20,6 +20,10 @@ pub fn gen_random_id() -> String {
     random_string::generate(6, random_string::charsets::ALPHANUMERIC)
 }
 
+pub fn gen_resume_token() -> String {
+    random_string::generate(32, random_string::charsets::ALPHANUMERIC)
+}
+
 pub fn get_random_word() -> &'static str {
     consts::WORDS[rand::thread_rng().gen_range(0..consts::WORDS.len())]
 }
@@ -31,17 +35,16 @@ pub fn choose_user_in_a_room_randomly<'st>(
     let mut indices = vec![];
 
     for (i, user) in users.iter().enumerate() {
-        if user.room_id == room_id {
+        if user.is_playing_in(room_id) {
             indices.push(i);
         }
     }
 
     Ok(users
         .get_mut(
-            indices
-                .get(rand::thread_rng().gen_range(0..indices.len()))
-                .ok_or("No user found in the room")?
-                .clone(),
+            *indices
+                .choose(&mut rand::thread_rng())
+                .ok_or("No user found in the room")?,
         )
         .ok_or("No user found in the room")?)
 }
@@ -63,12 +66,148 @@ pub fn turn_usize_to_vec_of_u8(value: usize) -> Vec<u8> {
     bytes
 }
 
+/// Encodes `value` as an unsigned LEB128 varint, i.e., 7 bits per byte starting
+/// from the least significant ones, with the highest bit set on every byte but
+/// the last.
+pub fn turn_usize_to_varint(value: usize) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut value = value;
+
+    loop {
+        let byte = (value & 0x7f) as u8;
+
+        value >>= 7;
+
+        if value == 0 {
+            bytes.push(byte);
+
+            return bytes;
+        }
+
+        bytes.push(byte | 0x80);
+    }
+}
+
+/// Reads the unsigned LEB128 varint that starts at `start` and returns it along
+/// with the position right after it.
+pub fn read_varint(
+    bytes: &[u8],
+    start: usize,
+) -> Result<(usize, usize), Box<dyn std::error::Error>> {
+    let mut value: usize = 0;
+    let mut shift = 0;
+    let mut position = start;
+
+    loop {
+        let byte = *bytes.get(position).ok_or("Data is too short")?;
+        let bits = (byte & 0x7f) as usize;
+
+        if shift >= usize::BITS || (bits << shift) >> shift != bits {
+            return Err("Length is too large".into());
+        }
+
+        value |= bits << shift;
+        shift += 7;
+        position += 1;
+
+        if byte & 0x80 == 0 {
+            return Ok((value, position));
+        }
+    }
+}
+
+/// Encodes the first point as two big endian `u16`s and every point after it as
+/// two big endian `i16`s with its difference from the previous point. Differences
+/// wrap around so any point can follow any other.
+pub fn encode_stroke_points(points: &[state::StrokePoint]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(points.len() * 4);
+    let mut previous = state::StrokePoint { x: 0, y: 0 };
+
+    for point in points {
+        bytes.extend(point.x.wrapping_sub(previous.x).to_be_bytes());
+        bytes.extend(point.y.wrapping_sub(previous.y).to_be_bytes());
+        previous = *point;
+    }
+
+    bytes
+}
+
+/// Decodes the points encoded with `encode_stroke_points`.
+pub fn decode_stroke_points(
+    bytes: &[u8],
+) -> Result<Vec<state::StrokePoint>, Box<dyn std::error::Error>> {
+    if !bytes.len().is_multiple_of(4) {
+        return Err("Data is invalid".into());
+    }
+
+    let mut points = Vec::with_capacity(bytes.len() / 4);
+    let mut previous = state::StrokePoint { x: 0, y: 0 };
+
+    for chunk in bytes.chunks_exact(4) {
+        let point = state::StrokePoint {
+            x: previous
+                .x
+                .wrapping_add(u16::from_be_bytes([chunk[0], chunk[1]])),
+            y: previous
+                .y
+                .wrapping_add(u16::from_be_bytes([chunk[2], chunk[3]])),
+        };
+
+        points.push(point);
+        previous = point;
+    }
+
+    Ok(points)
+}
+
 pub fn obfuscate_word(word: &str) -> String {
+    obfuscate_word_with_hints(word, &[])
+}
+
+/// Same as `obfuscate_word`, but leaves the characters whose indices are in
+/// `revealed_indices` as is.
+pub fn obfuscate_word_with_hints(word: &str, revealed_indices: &[usize]) -> String {
     word.chars()
-        .map(|c| if c.is_alphabetic() { '*' } else { c })
+        .enumerate()
+        .map(|(i, c)| {
+            if c.is_alphabetic() && !revealed_indices.contains(&i) {
+                '*'
+            } else {
+                c
+            }
+        })
         .collect()
 }
 
+/// Returns true if a letter of the word being drawn should be revealed when
+/// there is `time_left` out of `time_limit` seconds left.
+pub fn is_time_to_reveal_hint(time_left: u8, time_limit: u8) -> bool {
+    time_left != 0
+        && consts::HINT_PERCENTAGES_OF_TIME_LEFT
+            .iter()
+            .any(|percentage| {
+                time_left as u16 == time_limit as u16 * *percentage as u16 / 100
+            })
+}
+
+/// Picks the index of a random letter in `word` that has not been revealed yet.
+///
+/// Returns `None` if revealing another letter would leave no letter to guess.
+pub fn pick_letter_to_reveal(word: &str, revealed_indices: &[usize]) -> Option<usize> {
+    let hidden_indices = word
+        .chars()
+        .enumerate()
+        .filter(|(i, c)| c.is_alphabetic() && !revealed_indices.contains(i))
+        .map(|(i, _)| i)
+        .collect::<Vec<usize>>();
+
+    if hidden_indices.len() <= 1 {
+        return None;
+    }
+
+    Some(hidden_indices[rand::thread_rng().gen_range(0..hidden_indices.len())])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -80,10 +219,105 @@ mod tests {
         assert_eq!(bytes, vec![255, 45]);
     }
 
+    #[test]
+    fn test_turn_usize_to_varint() {
+        assert_eq!(turn_usize_to_varint(0), vec![0]);
+        assert_eq!(turn_usize_to_varint(127), vec![127]);
+        assert_eq!(turn_usize_to_varint(128), vec![0x80, 1]);
+        assert_eq!(turn_usize_to_varint(300), vec![0xac, 2]);
+        assert_eq!(turn_usize_to_varint(65536), vec![0x80, 0x80, 4]);
+    }
+
+    #[test]
+    fn test_read_varint() {
+        for value in [0, 1, 127, 128, 300, 65536, usize::MAX] {
+            let bytes = turn_usize_to_varint(value);
+
+            assert_eq!(read_varint(&bytes, 0).unwrap(), (value, bytes.len()));
+        }
+
+        assert_eq!(read_varint(&[9, 0xac, 2, 9], 1).unwrap(), (300, 3));
+        assert!(read_varint(&[0x80], 0).is_err());
+        assert!(read_varint(&[0xff; 11], 0).is_err());
+    }
+
+    #[test]
+    fn test_stroke_points() {
+        let points = vec![
+            state::StrokePoint { x: 1000, y: 65535 },
+            state::StrokePoint { x: 1002, y: 65530 },
+            state::StrokePoint { x: 65535, y: 0 },
+            state::StrokePoint { x: 0, y: 40000 },
+        ];
+        let bytes = encode_stroke_points(&points);
+
+        assert_eq!(bytes.len(), points.len() * 4);
+        assert_eq!(bytes[..8], [0x03, 0xe8, 0xff, 0xff, 0, 2, 0xff, 0xfb]);
+        assert_eq!(decode_stroke_points(&bytes).unwrap(), points);
+        assert!(decode_stroke_points(&[]).unwrap().is_empty());
+        assert!(decode_stroke_points(&bytes[..5]).is_err());
+    }
+
     #[test]
     fn test_obfuscate_word() {
         let word = "hello world";
         let obfuscated = obfuscate_word(word);
         assert_eq!(obfuscated, "***** *****");
     }
+
+    #[test]
+    fn test_obfuscate_word_with_hints() {
+        let word = "hello world";
+        let obfuscated = obfuscate_word_with_hints(word, &[0, 7]);
+        assert_eq!(obfuscated, "h**** *o***");
+    }
+
+    #[test]
+    fn test_is_time_to_reveal_hint() {
+        assert!(is_time_to_reveal_hint(30, 60));
+        assert!(is_time_to_reveal_hint(15, 60));
+        assert!(!is_time_to_reveal_hint(60, 60));
+        assert!(!is_time_to_reveal_hint(29, 60));
+        assert!(!is_time_to_reveal_hint(0, 1));
+    }
+
+    #[test]
+    fn test_pick_letter_to_reveal() {
+        let word = "ab c";
+
+        for _ in 0..20 {
+            let index = pick_letter_to_reveal(word, &[1]).unwrap();
+            assert!(index == 0 || index == 3);
+        }
+
+        assert_eq!(pick_letter_to_reveal(word, &[0, 1]), None);
+    }
+
+    #[test]
+    fn test_choose_user_in_a_room_randomly_skips_spectators() {
+        let mut users = vec![
+            state::UserBuilder::default()
+                .id("spectator".to_string())
+                .display_name("spectator".to_string())
+                .room_id("room".to_string())
+                .is_spectator(true)
+                .build()
+                .unwrap(),
+            state::UserBuilder::default()
+                .id("player".to_string())
+                .display_name("player".to_string())
+                .room_id("room".to_string())
+                .build()
+                .unwrap(),
+        ];
+
+        for _ in 0..20 {
+            let user = choose_user_in_a_room_randomly(&mut users, "room").unwrap();
+            assert_eq!(user.id, "player");
+        }
+
+        users.pop();
+
+        assert!(choose_user_in_a_room_randomly(&mut users, "room").is_err());
+    }
 }
@@ -0,0 +1,271 @@
+//! Word packs that are loaded from disk at startup.
+//!
+//! Every file in the word packs directory named `<category>.<language>.txt`,
+//! e.g., `animals.en.txt`, is a pack with one word per line. Empty lines and
+//! lines starting with `#` are ignored.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::{similarity, utils};
+
+/// The category of the pack made from the words embedded in the binary.
+pub const DEFAULT_CATEGORY: &str = "general";
+/// The language of the pack made from the words embedded in the binary.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct WordPack {
+    pub category: String,
+    pub language: String,
+    pub words: Vec<String>,
+}
+
+impl WordPack {
+    /// Parses the contents of a word pack file. Words that only differ in case
+    /// or accents from an earlier one are left out.
+    pub fn parse_words(contents: &str) -> Vec<String> {
+        let mut words: Vec<String> = Vec::new();
+        let mut seen = HashSet::new();
+
+        for line in contents.lines() {
+            let word = line.trim();
+
+            if word.is_empty() || word.starts_with('#') {
+                continue;
+            }
+
+            if !seen.insert(similarity::normalize(word)) {
+                continue;
+            }
+
+            words.push(word.to_string());
+        }
+
+        words
+    }
+
+    /// Returns the category and language of a word pack from its file name, or
+    /// `None` if the file is not a word pack.
+    pub fn parse_file_name(file_name: &str) -> Option<(String, String)> {
+        let (category, language) = file_name.strip_suffix(".txt")?.split_once('.')?;
+        let is_valid_part = |part: &str| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+
+        if !is_valid_part(category) || !is_valid_part(language) {
+            return None;
+        }
+
+        Some((category.to_lowercase(), language.to_lowercase()))
+    }
+}
+
+/// What the `/word-packs` endpoint returns for each pack.
+#[derive(rocket::serde::Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct WordPackSummary {
+    pub category: String,
+    pub language: String,
+    #[serde(rename = "amountOfWords")]
+    pub amount_of_words: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct WordPacks {
+    packs: Vec<Arc<WordPack>>,
+}
+
+impl Default for WordPacks {
+    /// Only has the pack made from the words embedded in the binary.
+    fn default() -> Self {
+        Self {
+            packs: vec![Arc::new(WordPack {
+                category: DEFAULT_CATEGORY.to_string(),
+                language: DEFAULT_LANGUAGE.to_string(),
+                words: utils::consts::WORDS
+                    .iter()
+                    .map(|word| word.to_string())
+                    .collect(),
+            })],
+        }
+    }
+}
+
+impl WordPacks {
+    /// Loads every word pack in `dir` on top of the default pack. A pack in `dir`
+    /// with the same category and language as the default pack replaces it.
+    ///
+    /// Having no directory at all is fine and results in only the default pack.
+    /// Files that cannot be read are skipped.
+    pub fn load(
+        dir: impl AsRef<std::path::Path>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut word_packs = Self::default();
+        let entries = match std::fs::read_dir(dir.as_ref()) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                eprintln!(
+                    "Word packs directory {:?} not found, using the default words only",
+                    dir.as_ref()
+                );
+
+                return Ok(word_packs);
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(err) => {
+                    eprintln!("Skipping an entry of {:?}: {}", dir.as_ref(), err);
+
+                    continue;
+                }
+            };
+            let Some((category, language)) = path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(WordPack::parse_file_name)
+            else {
+                eprintln!("Skipping {:?} since it is not a word pack", path);
+
+                continue;
+            };
+            let words = match std::fs::read_to_string(&path) {
+                Ok(contents) => WordPack::parse_words(&contents),
+                Err(err) => {
+                    eprintln!("Skipping {:?} since it could not be read: {}", path, err);
+
+                    continue;
+                }
+            };
+
+            if words.len() < utils::consts::MAX_AMOUNT_OF_WORDS_TO_PICK as usize {
+                eprintln!("Skipping {:?} since it does not have enough words", path);
+
+                continue;
+            }
+
+            word_packs
+                .packs
+                .retain(|pack| pack.category != category || pack.language != language);
+            word_packs.packs.push(Arc::new(WordPack {
+                category,
+                language,
+                words,
+            }));
+        }
+
+        word_packs
+            .packs
+            .sort_by(|a, b| (&a.category, &a.language).cmp(&(&b.category, &b.language)));
+
+        Ok(word_packs)
+    }
+
+    /// Finds a pack regardless of the case of `category` and `language`, as they
+    /// are lowercased when loaded.
+    pub fn get(&self, category: &str, language: &str) -> Option<Arc<WordPack>> {
+        let category = category.to_lowercase();
+        let language = language.to_lowercase();
+
+        self.packs
+            .iter()
+            .find(|pack| pack.category == category && pack.language == language)
+            .cloned()
+    }
+
+    pub fn summaries(&self) -> Vec<WordPackSummary> {
+        self.packs
+            .iter()
+            .map(|pack| WordPackSummary {
+                category: pack.category.clone(),
+                language: pack.language.clone(),
+                amount_of_words: pack.words.len(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_name() {
+        assert_eq!(
+            WordPack::parse_file_name("animals.en.txt"),
+            Some(("animals".to_string(), "en".to_string()))
+        );
+        assert_eq!(
+            WordPack::parse_file_name("Food.ES.txt"),
+            Some(("food".to_string(), "es".to_string()))
+        );
+        assert_eq!(WordPack::parse_file_name("animals.txt"), None);
+        assert_eq!(WordPack::parse_file_name("animals.en.md"), None);
+        assert_eq!(WordPack::parse_file_name(".en.txt"), None);
+        assert_eq!(WordPack::parse_file_name("a.b.c.txt"), None);
+    }
+
+    #[test]
+    fn test_parse_words() {
+        assert_eq!(
+            WordPack::parse_words("# comment\n cat \n\ndog\ncat\nCAT\ncafé\ncafe\n"),
+            vec!["cat", "dog", "café"]
+        );
+    }
+
+    #[test]
+    fn test_load_word_packs() {
+        let dir = std::env::temp_dir()
+            .join(format!("skribbl-word-packs-{}", utils::gen_random_id()));
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("animals.es.txt"),
+            "gato\nperro\npez\nvaca\nburro\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("too-small.en.txt"), "one\ntwo\n").unwrap();
+        std::fs::write(dir.join("README.md"), "not a pack").unwrap();
+        std::fs::write(dir.join("broken.en.txt"), [0xff, 0xfe, 0xfd]).unwrap();
+
+        let word_packs = WordPacks::load(&dir).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            word_packs.summaries(),
+            vec![
+                WordPackSummary {
+                    category: "animals".to_string(),
+                    language: "es".to_string(),
+                    amount_of_words: 5,
+                },
+                WordPackSummary {
+                    category: DEFAULT_CATEGORY.to_string(),
+                    language: DEFAULT_LANGUAGE.to_string(),
+                    amount_of_words: utils::consts::WORDS.len(),
+                },
+            ]
+        );
+        assert!(word_packs.get("animals", "es").is_some());
+        assert!(word_packs.get("Animals", "ES").is_some());
+        assert!(word_packs.get("broken", "en").is_none());
+        assert!(word_packs.get("too-small", "en").is_none());
+    }
+
+    #[test]
+    fn test_load_missing_word_packs_dir() {
+        let word_packs = WordPacks::load("this-directory-does-not-exist").unwrap();
+
+        assert_eq!(word_packs.summaries().len(), 1);
+        assert!(word_packs
+            .get(DEFAULT_CATEGORY, DEFAULT_LANGUAGE)
+            .is_some());
+    }
+}